@@ -0,0 +1,339 @@
+use std::path::{Path, PathBuf};
+
+use druid::image::{open, ImageFormat};
+use serde::Serialize;
+
+use crate::error::BotError;
+use crate::utils::draw_plan::DrawPlan;
+use crate::utils::game_profiles::{builtin_profile, BUILTIN_PROFILE_NAMES};
+use crate::utils::humanize::HumanizeConfig;
+use crate::utils::image_drawing::format_duration;
+use crate::utils::image_utils::{quantize, resize_to_area, Dithering};
+use crate::utils::plan_render::render_plan;
+use crate::utils::profile::Profile;
+use crate::utils::time_estimate::estimate_duration;
+
+const USAGE: &str = "\
+Usage: ImageDrawingBot plan --image <file> --size <W>x<H> [options]
+
+Prepares an image without opening a window and writes <name>.quantized.png,
+<name>.plan.json and <name>.simulated.png next to it, or into --out.
+
+Options:
+  --image <file>        PNG or JPEG picture to draw
+  --size <W>x<H>        size of the drawing area in pixels, the picture keeps its proportions
+  --palette <name>      gartic, skribbl, drawize, paint or a profile .json file (default: gartic)
+  --dither <mode>       none, floyd-steinberg or atkinson (default: floyd-steinberg)
+  --interval <pixels>   pixel interval between lines, 1 to 5 (default: 2)
+  --brush-width <px>    brush width of the simulation (default: 2)
+  --out <dir>           folder for the output files";
+
+struct PlanArgs {
+    image: PathBuf,
+    size: (u32, u32),
+    palette: String,
+    dithering: Dithering,
+    interval: u8,
+    brush_width: f64,
+    out: Option<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct PlanFile<'a> {
+    width: u32,
+    height: u32,
+    pixel_interval: u8,
+    strokes: usize,
+    plan: &'a DrawPlan,
+}
+
+// Runs the command line and returns the exit code. Without arguments the
+// window opens instead, so this is only called with some
+pub fn run(args: &[String]) -> i32 {
+    attach_console();
+    if args.first().is_some_and(|command| command == "help")
+        || args.iter().any(|arg| arg == "--help" || arg == "-h")
+    {
+        println!("{}", USAGE);
+        return 0;
+    }
+    let result = match args.first().map(String::as_str) {
+        Some("plan") => parse_plan_args(&args[1..]).and_then(|args| plan(&args)),
+        Some(command) => Err(BotError::Usage(format!("Unknown command \"{}\"", command))),
+        None => Err(BotError::Usage(String::from("No command given"))),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e @ BotError::Usage(_)) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            2
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+// The program has no console of its own, so without this nothing printed
+// shows up unless the output is redirected
+#[cfg(windows)]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // Fails when started without a console, e.g. from Explorer, which is fine
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+fn parse_plan_args(args: &[String]) -> Result<PlanArgs, BotError> {
+    let mut image = None;
+    let mut size = None;
+    let mut plan_args = PlanArgs {
+        image: PathBuf::new(),
+        size: (0, 0),
+        palette: String::from("gartic"),
+        dithering: Dithering::FloydSteinberg,
+        interval: 2,
+        brush_width: 2.0,
+        out: None,
+    };
+
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| BotError::Usage(format!("{} needs a value", flag)))?;
+        let invalid = || BotError::Usage(format!("Invalid value \"{}\" for {}", value, flag));
+        match flag.as_str() {
+            "--image" => image = Some(PathBuf::from(value)),
+            "--size" => {
+                let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+                let width: u32 = width.parse().map_err(|_| invalid())?;
+                let height: u32 = height.parse().map_err(|_| invalid())?;
+                if width == 0 || height == 0 {
+                    return Err(invalid());
+                }
+                size = Some((width, height));
+            }
+            "--palette" => plan_args.palette = value.clone(),
+            "--dither" => {
+                plan_args.dithering = match value.to_lowercase().as_str() {
+                    "none" => Dithering::None,
+                    "floyd-steinberg" | "fs" => Dithering::FloydSteinberg,
+                    "atkinson" => Dithering::Atkinson,
+                    _ => return Err(invalid()),
+                }
+            }
+            "--interval" => {
+                plan_args.interval = value
+                    .parse()
+                    .ok()
+                    .filter(|interval| (1..=5).contains(interval))
+                    .ok_or_else(invalid)?;
+            }
+            "--brush-width" => {
+                plan_args.brush_width = value
+                    .parse()
+                    .ok()
+                    .filter(|width: &f64| *width > 0.0)
+                    .ok_or_else(invalid)?;
+            }
+            "--out" => plan_args.out = Some(PathBuf::from(value)),
+            _ => return Err(BotError::Usage(format!("Unknown option \"{}\"", flag))),
+        }
+    }
+
+    plan_args.image = image.ok_or_else(|| BotError::Usage(String::from("--image is required")))?;
+    plan_args.size = size.ok_or_else(|| BotError::Usage(String::from("--size is required")))?;
+    Ok(plan_args)
+}
+
+// A built-in game by the start of its name, or a saved profile file
+fn load_palette(name: &str) -> Result<Profile, BotError> {
+    if name.to_lowercase().ends_with(".json") {
+        return Profile::load(Path::new(name));
+    }
+    let lowercase = name.to_lowercase();
+    BUILTIN_PROFILE_NAMES
+        .iter()
+        .find(|builtin| builtin.to_lowercase().starts_with(&lowercase))
+        .and_then(|builtin| builtin_profile(builtin))
+        .ok_or_else(|| BotError::Usage(format!("Unknown palette \"{}\"", name)))
+}
+
+fn plan(args: &PlanArgs) -> Result<(), BotError> {
+    let palette = load_palette(&args.palette)?.colors();
+    if palette.is_empty() {
+        return Err(BotError::EmptyPalette);
+    }
+
+    let source = resize_to_area(&open(&args.image)?, args.size);
+    let quantized = quantize(&source, &palette, args.dithering);
+    let plan = DrawPlan::from_image(&quantized, args.interval);
+    let simulated = render_plan(&plan, quantized.dimensions(), args.brush_width);
+
+    let stem = args
+        .image
+        .file_stem()
+        .map_or(String::from("image"), |stem| {
+            stem.to_string_lossy().into_owned()
+        });
+    let out = match &args.out {
+        Some(out) => out.clone(),
+        None => args
+            .image
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf),
+    };
+    std::fs::create_dir_all(&out)?;
+
+    let quantized_path = out.join(format!("{}.quantized.png", stem));
+    quantized
+        .save_with_format(&quantized_path, ImageFormat::Png)
+        .map_err(BotError::SaveImage)?;
+    let simulated_path = out.join(format!("{}.simulated.png", stem));
+    simulated
+        .save_with_format(&simulated_path, ImageFormat::Png)
+        .map_err(BotError::SaveImage)?;
+    let plan_path = out.join(format!("{}.plan.json", stem));
+    let (width, height) = quantized.dimensions();
+    let plan_file = PlanFile {
+        width,
+        height,
+        pixel_interval: args.interval,
+        strokes: plan.stroke_count(),
+        plan: &plan,
+    };
+    let json = serde_json::to_string_pretty(&plan_file).map_err(BotError::Json)?;
+    std::fs::write(&plan_path, json)?;
+
+    println!(
+        "{}x{}, {} colors, {} strokes, ~{}",
+        width,
+        height,
+        plan.colors.len(),
+        plan.stroke_count(),
+        format_duration(estimate_duration(&plan, None, &HumanizeConfig::default()))
+    );
+    for path in [quantized_path, plan_path, simulated_path] {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::game_profiles::{GARTIC_PHONE, PAINT, SKRIBBL_IO};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn parse(arguments: &[&str]) -> Result<PlanArgs, BotError> {
+        parse_plan_args(&args(arguments))
+    }
+
+    fn usage_error(result: Result<PlanArgs, BotError>) -> String {
+        match result {
+            Err(BotError::Usage(message)) => message,
+            Err(e) => panic!("not a usage error: {}", e),
+            Ok(_) => panic!("parsed"),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&["--image", "cat.png", "--size", "640x480"]).unwrap();
+        assert_eq!(args.image, PathBuf::from("cat.png"));
+        assert_eq!(args.size, (640, 480));
+        assert_eq!(args.palette, "gartic");
+        assert_eq!(args.dithering, Dithering::FloydSteinberg);
+        assert_eq!(args.interval, 2);
+        assert_eq!(args.brush_width, 2.0);
+        assert_eq!(args.out, None);
+    }
+
+    #[test]
+    fn every_option() {
+        let args = parse(&[
+            "--size",
+            "300X200",
+            "--image",
+            "cat.png",
+            "--palette",
+            "skr",
+            "--dither",
+            "Atkinson",
+            "--interval",
+            "3",
+            "--brush-width",
+            "1.5",
+            "--out",
+            "out",
+        ])
+        .unwrap();
+        assert_eq!(args.size, (300, 200));
+        assert_eq!(args.palette, "skr");
+        assert_eq!(args.dithering, Dithering::Atkinson);
+        assert_eq!(args.interval, 3);
+        assert_eq!(args.brush_width, 1.5);
+        assert_eq!(args.out, Some(PathBuf::from("out")));
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        for size in ["640", "640x", "x480", "0x480", "640x-1", "axb"] {
+            let message = usage_error(parse(&["--image", "cat.png", "--size", size]));
+            assert!(message.contains("--size"), "{}: {}", size, message);
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        assert!(usage_error(parse(&["--dither", "ordered"])).contains("--dither"));
+        assert!(usage_error(parse(&["--interval", "6"])).contains("--interval"));
+        assert!(usage_error(parse(&["--brush-width", "0"])).contains("--brush-width"));
+    }
+
+    #[test]
+    fn missing_arguments() {
+        assert_eq!(
+            usage_error(parse(&["--size", "10x10"])),
+            "--image is required"
+        );
+        assert_eq!(
+            usage_error(parse(&["--image", "cat.png"])),
+            "--size is required"
+        );
+        assert_eq!(usage_error(parse(&["--image"])), "--image needs a value");
+        assert_eq!(
+            usage_error(parse(&["--colors", "4"])),
+            "Unknown option \"--colors\""
+        );
+    }
+
+    #[test]
+    fn help_anywhere() {
+        assert_eq!(run(&args(&["--help"])), 0);
+        assert_eq!(run(&args(&["plan", "--image", "cat.png", "-h"])), 0);
+        assert_eq!(run(&args(&["help"])), 0);
+        assert_eq!(run(&args(&["draw"])), 2);
+    }
+
+    #[test]
+    fn palettes_by_name_prefix() {
+        assert_eq!(load_palette("skr").unwrap().name, SKRIBBL_IO);
+        assert_eq!(load_palette("GARTIC").unwrap().name, GARTIC_PHONE);
+        assert_eq!(load_palette("paint").unwrap().name, PAINT);
+        assert!(matches!(load_palette("photoshop"), Err(BotError::Usage(_))));
+    }
+}
@@ -0,0 +1,118 @@
+use std::{fmt, io};
+
+use druid::image::{ImageError, Rgb};
+
+use crate::utils::image_utils::color_hex;
+use crate::utils::preflight::Preflight;
+
+#[derive(Debug)]
+pub enum BotError {
+    AreaNotSelected,
+    Usage(String),
+    Preflight(Preflight),
+    MissingColorPosition(Rgb<u8>),
+    InvalidHexColor { index: usize, value: String },
+    EmptyPalette,
+    UnknownPaletteColor(String),
+    PaletteNotDetected,
+    CanvasNotDetected,
+    CheckpointMismatch,
+    InvalidCoordinate { line: usize, value: String },
+    Profile(serde_json::Error),
+    UnsupportedProfileVersion(u32),
+    Checkpoint(serde_json::Error),
+    Json(serde_json::Error),
+    Image(ImageError),
+    SaveImage(ImageError),
+    Input(rdev::SimulateError),
+    Io(io::Error),
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::AreaNotSelected => f.write_str(
+                "No drawing area is selected. Click \"Select area\" and drag a rectangle over the canvas first.",
+            ),
+            BotError::Usage(message) => f.write_str(message),
+            BotError::Preflight(preflight) => {
+                write!(f, "Drawing can't start yet:\n\n{}", preflight)
+            }
+            BotError::MissingColorPosition(color) => write!(
+                f,
+                "Color {} has no calibrated button position. Click \"Select palette\" and mark every color button.",
+                color_hex(color)
+            ),
+            BotError::InvalidHexColor { index, value } => write!(
+                f,
+                "Color {} has an invalid hex value \"{}\". Use the #RRGGBB form, e.g. #FF8800.",
+                index + 1,
+                value
+            ),
+            BotError::EmptyPalette => {
+                f.write_str("The palette has no colors. Add at least one color before saving.")
+            }
+            BotError::UnknownPaletteColor(value) => write!(
+                f,
+                "\"{}\" is not in the palette. Enter the number of a color, as shown while selecting the palette, or its #RRGGBB value.",
+                value
+            ),
+            BotError::PaletteNotDetected => f.write_str(
+                "None of the palette colors were found in the screenshot. Take a full screen screenshot with the game's palette visible, or click \"Select palette\" to mark the buttons by hand.",
+            ),
+            BotError::CanvasNotDetected => f.write_str(
+                "No white canvas was found in the screenshot. Take a full screen screenshot with an empty canvas, or click \"Select area\" to select it by hand.",
+            ),
+            BotError::CheckpointMismatch => f.write_str(
+                "The image or the drawing settings changed since drawing was interrupted, so it can't be resumed. Click \"Restart\" to draw from the beginning.",
+            ),
+            BotError::InvalidCoordinate { line, value } => write!(
+                f,
+                "Invalid coordinate \"{}\" on line {} of the saved palette positions. Click \"Select palette\" to calibrate it again.",
+                value, line
+            ),
+            BotError::Profile(e) => write!(
+                f,
+                "The calibration profile is damaged ({}). Fix or delete the profile file and calibrate again.",
+                e
+            ),
+            BotError::UnsupportedProfileVersion(version) => write!(
+                f,
+                "The calibration profile was saved by a newer version (format {}). Update the bot or calibrate again.",
+                version
+            ),
+            BotError::Checkpoint(e) => write!(
+                f,
+                "Could not save the drawing progress ({}). Drawing can't be resumed if it is interrupted.",
+                e
+            ),
+            BotError::Json(e) => write!(f, "Could not write the JSON output: {}", e),
+            BotError::Image(e) => write!(f, "Could not read the image: {}. Pick a PNG or JPEG file.", e),
+            BotError::SaveImage(e) => write!(f, "Could not save the image: {}. Pick another file name or folder.", e),
+            BotError::Input(_) => f.write_str(
+                "Could not send mouse input. Check that the bot is allowed to control the mouse.",
+            ),
+            BotError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<io::Error> for BotError {
+    fn from(e: io::Error) -> Self {
+        BotError::Io(e)
+    }
+}
+
+impl From<ImageError> for BotError {
+    fn from(e: ImageError) -> Self {
+        BotError::Image(e)
+    }
+}
+
+impl From<rdev::SimulateError> for BotError {
+    fn from(e: rdev::SimulateError) -> Self {
+        BotError::Input(e)
+    }
+}
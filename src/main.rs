@@ -1,120 +1,298 @@
 #![windows_subsystem = "windows"]
 
+mod cli;
+mod error;
 mod ui;
 mod utils;
 
 use druid::commands::CLOSE_WINDOW;
 use druid::image::{open, ImageBuffer, Rgb};
 
-use druid::widget::{Container, Image};
 use druid::{
-    AppLauncher, Data, Env, EventCtx, ImageBuf, Lens, LocalizedString, Rect, Screen, Size,
-    WidgetExt, WindowConfig, WindowDesc, WindowHandle, WindowId,
+    AppLauncher, Data, Env, EventCtx, Lens, LocalizedString, Point, Rect, Screen, SingleUse, Size,
+    Target, WidgetExt, WindowConfig, WindowDesc, WindowHandle, WindowId,
 };
 
+use error::BotError;
 use rfd::FileDialog;
-use std::sync::{mpsc, Arc};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::thread;
-use ui::controllers::DragController;
-use ui::ui::{build_root_widget, ViewStatus};
-use utils::image_drawing::draw_image;
-use utils::image_utils::quantize;
-use utils::save_coordinates::load_colors_pos;
+use std::time::Duration;
+use ui::commands::{
+    LoadedImage, PaletteEdit, PreparedImage, DRAW_FINISHED, DRAW_PROGRESS, HOTKEY_ACTION,
+    IMAGE_LOADED,
+};
+use ui::controllers::PreviewWindowController;
+use ui::ui::{build_preview, build_root_widget, PaletteEditorEntry, ViewStatus};
+use utils::canvas_detection::detect_canvas;
+use utils::checkpoint::{clear_checkpoint, load_checkpoint, Checkpoint};
+use utils::draw_plan::{DrawPlan, BACKGROUND};
+use utils::fail_safe::{FailSafe, FailSafeConfig};
+use utils::game_profiles::{builtin_profile, GARTIC_PHONE};
+use utils::hotkeys::{load_hotkey_bindings, HotkeyService};
+use utils::humanize::HumanizeConfig;
+use utils::image_drawing::{draw_image, format_duration, DrawJob, DrawProgress, Verification};
+use utils::image_metrics::{psnr, ssim};
+use utils::image_utils::{
+    color_hex, crop_screenshot, next_image_path, parse_hex_color, quantize, resize_to_area,
+    Dithering,
+};
+use utils::palette_check::check_palette;
+use utils::palette_detection::detect_palette_buttons;
+use utils::plan_render::{render_plan, render_plan_on};
+use utils::preflight::{preflight, PreflightInput};
+use utils::profile::{
+    load_or_migrate_profile, load_selected_profile_name, save_selected_profile_name, Profile,
+    ScreenInfo, ERASER_TOOL,
+};
+use utils::screen_source::{FileScreenSource, ScreenSource};
+use utils::screen_space::ScreenSpace;
+use utils::time_estimate::estimate_duration;
 
 #[derive(Clone, Data, Lens)]
 struct AppState {
     #[data(eq)]
     palette: Arc<Vec<Rgb<u8>>>,
+    // The loaded image fitted into the area, before quantization
+    #[data(eq)]
+    source_image: ImageBuffer<Rgb<u8>, Vec<u8>>,
     #[data(eq)]
     current_image: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    // Size of the area the images were fitted into, they keep their proportions
+    image_area: Option<(u32, u32)>,
+    // How the canvas will look after drawing current_image
+    #[data(eq)]
+    simulated_image: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    preview_stats: String,
+    // What the drawing area already shows, only the differences are drawn
     #[data(eq)]
-    colors_pos: Vec<(f64, f64)>,
+    canvas_image: Option<ImageBuffer<Rgb<u8>, Vec<u8>>>,
+    #[data(eq)]
+    estimated_time: Option<Duration>,
+    #[data(eq)]
+    // Button position of every palette color, None while not calibrated
+    colors_pos: Vec<Option<(f64, f64)>>,
+    #[data(eq)]
+    // Palette indices whose button showed another color in the last check
+    palette_mismatches: Vec<usize>,
+    // Palette index the next click places, otherwise the first uncalibrated one
+    palette_target: Option<usize>,
+    // Palette selection ends once the target is placed
+    palette_single: bool,
+    // Number or hex value of the color to calibrate by itself
+    recalibrate_color: String,
     #[data(eq)]
     sub_window: Option<WindowId>,
+    preview_window: Option<Rect>,
+    #[data(eq)]
+    image_path: Option<PathBuf>,
+    loading_image: bool,
+    // Bumped for every load so results of an outdated load are dropped
+    #[data(ignore)]
+    image_generation: u64,
+    #[data(ignore)]
+    hotkeys: HotkeyService,
     dithering: bool,
     pixel_interval: f64,
+    brush_width: f64,
+    fail_safe: FailSafeConfig,
+    humanize: HumanizeConfig,
+    drawing: bool,
+    // Screenshot file the canvas is checked against after drawing, and how
+    // many times missed pixels are redrawn
+    #[data(eq)]
+    verify_screenshot: Option<PathBuf>,
+    verify_rounds: f64,
+    // Where an interrupted drawing stopped
+    #[data(eq)]
+    checkpoint: Option<Checkpoint>,
+    #[data(eq)]
+    progress: Option<DrawProgress>,
+    status: String,
     view_status: ViewStatus,
     start_pos: Option<(f64, f64)>,
     end_pos: Option<(f64, f64)>,
-    x_offset: f64,
-    y_offset: f64,
+    // Keep the aspect ratio of the loaded image while editing the area
+    lock_aspect: bool,
+    selected_profile: String,
+    #[data(eq)]
+    profile: Profile,
+    // Working copy of the palette while the palette editor is open
+    palette_editor: Arc<Vec<PaletteEditorEntry>>,
+    editor_screenshot: Option<Arc<ImageBuffer<Rgb<u8>, Vec<u8>>>>,
 }
 
 impl AppState {
-    fn get_image(&self) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>> {
-        if let Some(selected_path) = FileDialog::new().pick_file() {
-            let palette = Arc::clone(&self.palette);
-            let dithering = self.dithering.clone();
-
-            let pos1 = self.start_pos.unwrap();
-            let pos2 = self.end_pos.unwrap();
-
-            let size = Rect::from_points(pos1, pos2).size();
-            let (tx, rx) = mpsc::channel();
-
-            // Spawn a new thread to open the image
-            thread::spawn(move || match open(&selected_path) {
-                Ok(image) => {
-                    let result = quantize(
-                        &image,
-                        &palette,
-                        (size.width as u32, size.height as u32),
-                        dithering,
-                    );
-                    tx.send(result).unwrap();
-                }
-                Err(e) => panic!("Failed proccesing image: {}", e),
+    // Decodes and quantizes on a worker thread, the result comes back as IMAGE_LOADED
+    fn get_image(&mut self, ctx: &mut EventCtx, path: PathBuf) -> Result<(), BotError> {
+        if self.palette.is_empty() {
+            return Err(BotError::EmptyPalette);
+        }
+        let palette = Arc::clone(&self.palette);
+        let dithering = if self.dithering {
+            Dithering::FloydSteinberg
+        } else {
+            Dithering::None
+        };
+
+        let size = self.area().ok_or(BotError::AreaNotSelected)?.size();
+        let event_sink = ctx.get_external_handle();
+
+        self.loading_image = true;
+        self.image_generation += 1;
+        let generation = self.image_generation;
+        thread::spawn(move || {
+            let image = open(&path)
+                .map(|image| {
+                    let area_size = (size.width as u32, size.height as u32);
+                    let source = resize_to_area(&image, area_size);
+                    let quantized = quantize(&source, &palette, dithering);
+                    PreparedImage {
+                        source,
+                        quantized,
+                        area_size,
+                    }
+                })
+                .map_err(BotError::from);
+            let loaded = SingleUse::new(LoadedImage {
+                path,
+                generation,
+                image,
             });
+            let _ = event_sink.submit_command(IMAGE_LOADED, loaded, Target::Auto);
+        });
+        Ok(())
+    }
 
-            // Получаем размеры изображения из потока
-            match rx.recv() {
-                Ok(image) => return Some(image),
-                Err(e) => panic!("Failed to get image: {}", e),
-            }
-        } else {
-            None
+    fn save_image(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        // Check before the file dialog so the user doesn't pick a file for nothing
+        if self.area().is_none() {
+            return Err(BotError::AreaNotSelected);
+        }
+        match FileDialog::new().pick_file() {
+            Some(path) => self.get_image(ctx, path),
+            None => Ok(()),
+        }
+    }
+
+    fn load_next_image(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        match self.image_path.as_deref().and_then(next_image_path) {
+            Some(path) => self.get_image(ctx, path),
+            None => Ok(()),
         }
     }
 
-    fn save_image(&mut self, ctx: &mut EventCtx, data: &AppState, env: &Env) {
-        self.current_image = self.get_image().unwrap_or_default();
+    // The existing canvas under the image, if one was loaded for the current area
+    fn canvas_under_image(&self) -> Option<ImageBuffer<Rgb<u8>, Vec<u8>>> {
+        let canvas = self.canvas_image.as_ref()?;
+        let (width, height) = self.current_image.dimensions();
+        if canvas.width() < width || canvas.height() < height {
+            return None;
+        }
+        Some(druid::image::imageops::crop_imm(canvas, 0, 0, width, height).to_image())
+    }
+
+    fn pick_verify_screenshot(&mut self) {
+        if let Some(path) = FileDialog::new().pick_file() {
+            self.verify_screenshot = Some(path);
+        }
+    }
+
+    fn build_plan(&self) -> DrawPlan {
+        let pixels_interval = self.pixel_interval as u8;
+        match self.canvas_under_image() {
+            Some(canvas) => DrawPlan::from_diff(&self.current_image, &canvas, pixels_interval),
+            None => DrawPlan::from_image(&self.current_image, pixels_interval),
+        }
+    }
+
+    // The drawing area in the physical pixels of a full screen screenshot
+    fn area_in_screenshot(&self, window: &WindowHandle) -> Option<Rect> {
+        Some(ScreenSpace::current(window).logical_rect_to_screenshot(self.area()?))
+    }
+
+    // Takes a full screen screenshot of the game, the drawing area is cut out of it
+    fn load_canvas_screenshot(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let area = self.area().ok_or(BotError::AreaNotSelected)?;
+        let Some(path) = FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let screenshot = open(path)?.to_rgb8();
+        let pixels = self
+            .area_in_screenshot(ctx.window())
+            .ok_or(BotError::AreaNotSelected)?;
+
+        self.canvas_image = Some(crop_screenshot(
+            &screenshot,
+            pixels,
+            (area.width() as u32, area.height() as u32),
+        ));
+        self.update_simulation();
+        Ok(())
+    }
+
+    fn update_simulation(&mut self) {
+        if self.image_path.is_none() {
+            return;
+        }
+        let plan = self.build_plan();
+        let estimated_time =
+            estimate_duration(&plan, self.profile.timings.as_ref(), &self.humanize);
+        self.estimated_time = Some(estimated_time);
+        self.simulated_image = match self.canvas_under_image() {
+            Some(canvas) => render_plan_on(canvas, &plan, self.brush_width),
+            None => render_plan(&plan, self.current_image.dimensions(), self.brush_width),
+        };
+        // How much of the quantized image survives the pixel interval and brush
+        self.preview_stats = format!(
+            "{} colors, {} strokes, about {}\nPSNR {:.1} dB, SSIM {:.3}",
+            plan.colors.len(),
+            plan.stroke_count(),
+            format_duration(estimated_time),
+            psnr(&self.current_image, &self.simulated_image),
+            ssim(&self.current_image, &self.simulated_image)
+        );
+    }
 
-        self.show_image(ctx, data, env)
+    fn save_simulation(&self) -> Result<(), BotError> {
+        let Some(path) = FileDialog::new()
+            .add_filter("PNG", &["png"])
+            .set_file_name("simulation.png")
+            .save_file()
+        else {
+            return Ok(());
+        };
+        self.simulated_image
+            .save_with_format(path, druid::image::ImageFormat::Png)
+            .map_err(BotError::SaveImage)
     }
 
     fn show_image(&mut self, ctx: &mut EventCtx, data: &AppState, env: &Env) {
-        let widget = Container::new(Image::new(ImageBuf::from_raw(
-            self.current_image.clone().into_raw(),
-            druid::piet::ImageFormat::Rgb,
-            self.current_image.width() as usize,
-            self.current_image.height() as usize,
-        )));
+        // Room for the tabs and the stats footer
         let size = Size::new(
-            self.current_image.width() as f64,
-            self.current_image.height() as f64,
+            (self.current_image.width() as f64 + 20.0).clamp(400.0, 1000.0),
+            (self.current_image.height() as f64 + 90.0).clamp(300.0, 800.0),
         );
 
-        if self.sub_window.is_some() {
-            ctx.submit_command(CLOSE_WINDOW.to(self.sub_window.unwrap()));
+        if let Some(sub_window) = self.sub_window {
+            ctx.submit_command(CLOSE_WINDOW.to(sub_window));
         }
+        self.preview_window = None;
 
-        self.sub_window = Some(
-            ctx.new_sub_window(
-                WindowConfig::default()
-                    .show_titlebar(false)
-                    .window_size(size),
-                widget.controller(DragController),
-                data.clone(),
-                env.clone(),
-            ),
-        );
+        self.sub_window = Some(ctx.new_sub_window(
+            WindowConfig::default().window_size(size).resizable(true),
+            build_preview().controller(PreviewWindowController::default()),
+            data.clone(),
+            env.clone(),
+        ));
     }
 
     fn switch_view_to_overlay(&self, window: &WindowHandle) {
-        let display_rect = Screen::get_display_rect();
-        let display_size = display_rect.size();
+        let display_rect = ScreenSpace::current(window).logical_bounds();
 
-        window.set_size(display_size);
+        window.set_size(display_rect.size());
         window.set_always_on_top(true);
         window.show_titlebar(false);
         window.set_position(display_rect.origin());
@@ -123,10 +301,34 @@ impl AppState {
     fn switch_view_to_default(&self, window: &WindowHandle) {
         window.set_always_on_top(false);
         window.show_titlebar(true);
-        window.set_size((300.0, 300.0));
+        window.set_size((320.0, 760.0));
+        window.set_position(Screen::get_display_rect().center());
+    }
+
+    fn switch_view_to_editor(&self, window: &WindowHandle) {
+        window.set_size((460.0, 720.0));
         window.set_position(Screen::get_display_rect().center());
     }
 
+    // The drawing area in screen coordinates, whichever way it was dragged
+    fn area(&self) -> Option<Rect> {
+        self.start_pos
+            .zip(self.end_pos)
+            .map(|(start, end)| Rect::from_points(start, end))
+    }
+
+    fn set_area(&mut self, area: Rect) {
+        let area = area.abs();
+        self.start_pos = Some(area.origin().into());
+        self.end_pos = Some((area.x1, area.y1));
+    }
+
+    // Width to height ratio the area has to keep, if locked to a loaded image
+    fn area_aspect(&self) -> Option<f64> {
+        let (width, height) = self.current_image.dimensions();
+        (self.lock_aspect && self.image_path.is_some()).then(|| width as f64 / height as f64)
+    }
+
     fn get_area(&mut self, ctx: &mut EventCtx) {
         self.switch_view_to_overlay(ctx.window());
         self.view_status = ViewStatus::Area;
@@ -134,85 +336,535 @@ impl AppState {
 
     fn get_palette(&mut self, ctx: &mut EventCtx) {
         self.switch_view_to_overlay(ctx.window());
-        self.colors_pos = Vec::with_capacity(18);
+        self.colors_pos = vec![None; self.palette.len()];
+        self.palette_mismatches.clear();
+        self.palette_target = None;
+        self.palette_single = false;
+        self.view_status = ViewStatus::Palette;
+    }
+
+    // Places the button of a single color, the others keep their positions
+    fn recalibrate_palette_color(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let value = self.recalibrate_color.trim();
+        let index = match value.parse::<usize>() {
+            Ok(number) => number
+                .checked_sub(1)
+                .filter(|&index| index < self.palette.len()),
+            Err(_) => parse_hex_color(value)
+                .and_then(|color| self.palette.iter().position(|&entry| entry == color)),
+        }
+        .ok_or_else(|| BotError::UnknownPaletteColor(value.to_string()))?;
+
+        self.colors_pos.resize(self.palette.len(), None);
+        self.palette_target = Some(index);
+        self.palette_single = true;
+        self.switch_view_to_overlay(ctx.window());
         self.view_status = ViewStatus::Palette;
+        Ok(())
     }
 
-    fn draw_image(&self) {
+    // The palette index the next click in the palette overlay places
+    fn next_palette_slot(&self) -> Option<usize> {
+        self.palette_target
+            .or_else(|| self.colors_pos.iter().position(Option::is_none))
+    }
+
+    // Samples every calibrated button in a full screen screenshot. Buttons that
+    // show another color are marked in the palette overlay
+    fn verify_palette(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let Some(path) = FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let screen = ScreenSpace::current(ctx.window());
+        let bounds = screen.physical_bounds();
+        let mut source = FileScreenSource {
+            path,
+            rect: bounds.with_origin(Point::ZERO),
+            size: (bounds.width() as u32, bounds.height() as u32),
+            last_modified: None,
+        };
+        let Some(screenshot) = source.capture()? else {
+            return Ok(());
+        };
+        let points: Vec<Option<(f64, f64)>> = self
+            .colors_pos
+            .iter()
+            .map(|pos| pos.map(|pos| screen.logical_to_screenshot(pos.into()).into()))
+            .collect();
+
+        let mismatches = check_palette(&screenshot, &self.palette, &points);
+        self.palette_mismatches = mismatches.iter().map(|mismatch| mismatch.index).collect();
+        if mismatches.is_empty() {
+            let calibrated = points.iter().filter(|point| point.is_some()).count();
+            self.status = format!("All {} calibrated colors match", calibrated);
+            return Ok(());
+        }
+        let details: Vec<String> = mismatches
+            .iter()
+            .map(|mismatch| {
+                let found = mismatch
+                    .found
+                    .map_or(String::from("off screen"), |found| color_hex(&found));
+                format!(
+                    "{}. {} is {}",
+                    mismatch.index + 1,
+                    color_hex(&mismatch.expected),
+                    found
+                )
+            })
+            .collect();
+        self.status = format!("Wrong palette buttons: {}", details.join(", "));
+        self.switch_view_to_overlay(ctx.window());
+        self.view_status = ViewStatus::PaletteVerify;
+        Ok(())
+    }
+
+    // Forgets the buttons that failed the check, so only they are clicked again
+    fn reclick_mismatches(&mut self) {
+        for &index in &self.palette_mismatches {
+            if let Some(pos) = self.colors_pos.get_mut(index) {
+                *pos = None;
+            }
+        }
+        self.palette_mismatches.clear();
+        self.palette_target = None;
+        self.palette_single = false;
+        self.view_status = ViewStatus::Palette;
+    }
+
+    // Proposes the white canvas of a full screen screenshot as the drawing area.
+    // The area overlay opens with it, so it can be accepted or selected again
+    fn detect_area(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let Some(path) = FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let canvas = detect_canvas(&open(path)?.to_rgb8()).ok_or(BotError::CanvasNotDetected)?;
+
+        let screen = ScreenSpace::current(ctx.window());
+        let canvas = Rect::from_points(
+            screen.screenshot_to_logical(canvas.origin().into()),
+            screen.screenshot_to_logical((canvas.x1, canvas.y1)),
+        );
+        // With the canvas as the anchor the palette moves along with it
+        let anchor = self.profile.anchor;
+        if anchor.is_some() && self.area().map(|area| area.origin().into()) == anchor {
+            self.profile.move_anchor(canvas.origin().into());
+            self.colors_pos = self.profile.colors_pos();
+        }
+        self.set_area(canvas);
+        self.get_area(ctx);
+        Ok(())
+    }
+
+    // Opens the overlay for a single click, on the anchor or a tool button
+    fn get_point(&mut self, ctx: &mut EventCtx, view_status: ViewStatus) {
+        self.switch_view_to_overlay(ctx.window());
+        self.view_status = view_status;
+    }
+
+    // Calibrations become relative to `anchor` and stay where they are
+    fn set_anchor(&mut self, window: &WindowHandle, anchor: (f64, f64)) -> Result<(), BotError> {
+        self.profile.set_anchor(anchor);
+        self.status = String::from("Anchor set");
+        self.save_profile(window)
+    }
+
+    fn set_eraser(&mut self, window: &WindowHandle, pos: (f64, f64)) -> Result<(), BotError> {
+        self.profile.set_tool(ERASER_TOOL, pos);
+        self.status = String::from("Eraser button set");
+        self.save_profile(window)
+    }
+
+    // The game moved on the screen, the anchor and every calibration move with it
+    fn move_anchor(&mut self, window: &WindowHandle, anchor: (f64, f64)) -> Result<(), BotError> {
+        let Some(old) = self.profile.anchor else {
+            return self.set_anchor(window, anchor);
+        };
+        self.profile.move_anchor(anchor);
+        self.colors_pos = self.profile.colors_pos();
+        if let Some(canvas) = self.profile.canvas_rect() {
+            self.set_area(canvas);
+        }
+        self.status = format!(
+            "Calibration moved by {:.0}, {:.0}",
+            anchor.0 - old.0,
+            anchor.1 - old.1
+        );
+        self.save_profile(window)
+    }
+
+    // Finds the palette buttons in a full screen screenshot and shows them for
+    // confirmation, the profile is only saved once the user accepts them
+    fn detect_palette(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let Some(path) = FileDialog::new().pick_file() else {
+            return Ok(());
+        };
+        let screenshot = open(path)?.to_rgb8();
+        let detected = detect_palette_buttons(&screenshot, &self.palette);
+        if detected.iter().all(Option::is_none) {
+            return Err(BotError::PaletteNotDetected);
+        }
+
+        let screen = ScreenSpace::current(ctx.window());
+        self.colors_pos = detected
+            .into_iter()
+            .map(|pos| pos.map(|pos| screen.screenshot_to_logical(pos).into()))
+            .collect();
+
+        let missing: Vec<String> = self
+            .palette
+            .iter()
+            .zip(&self.colors_pos)
+            .filter(|(_, pos)| pos.is_none())
+            .map(|(color, _)| color_hex(color))
+            .collect();
+        self.status = if missing.is_empty() {
+            String::from("All palette colors were found")
+        } else {
+            format!("Not found in the screenshot: {}", missing.join(", "))
+        };
+
+        self.switch_view_to_overlay(ctx.window());
+        self.view_status = ViewStatus::PaletteConfirm;
+        Ok(())
+    }
+
+    fn open_palette_editor(&mut self, ctx: &mut EventCtx) {
+        self.palette_editor = Arc::new(
+            self.palette
+                .iter()
+                .enumerate()
+                .map(|(index, color)| PaletteEditorEntry {
+                    index,
+                    hex: color_hex(color),
+                    position: self.colors_pos.get(index).copied().flatten(),
+                })
+                .collect(),
+        );
+        self.editor_screenshot = None;
+        self.switch_view_to_editor(ctx.window());
+        self.view_status = ViewStatus::PaletteEditor;
+    }
+
+    fn close_palette_editor(&mut self, ctx: &mut EventCtx) {
+        self.palette_editor = Arc::new(Vec::new());
+        self.editor_screenshot = None;
+        self.view_status = ViewStatus::Default;
+        self.switch_view_to_default(ctx.window());
+    }
+
+    fn add_palette_color(&mut self, color: Rgb<u8>) {
+        let entries = Arc::make_mut(&mut self.palette_editor);
+        entries.push(PaletteEditorEntry {
+            index: entries.len(),
+            hex: color_hex(&color),
+            position: None,
+        });
+    }
+
+    fn edit_palette(&mut self, edit: PaletteEdit) {
+        let entries = Arc::make_mut(&mut self.palette_editor);
+        match edit {
+            PaletteEdit::MoveUp(index) if index > 0 && index < entries.len() => {
+                entries.swap(index - 1, index)
+            }
+            PaletteEdit::MoveDown(index) if index + 1 < entries.len() => {
+                entries.swap(index, index + 1)
+            }
+            PaletteEdit::Remove(index) if index < entries.len() => {
+                entries.remove(index);
+            }
+            _ => return,
+        }
+        for (index, entry) in entries.iter_mut().enumerate() {
+            entry.index = index;
+        }
+    }
+
+    fn load_editor_screenshot(&mut self) -> Result<(), BotError> {
+        if let Some(path) = FileDialog::new().pick_file() {
+            self.editor_screenshot = Some(Arc::new(open(path)?.to_rgb8()));
+        }
+        Ok(())
+    }
+
+    // Applies the edited palette and stores it in the profile of the current game.
+    // Positions move with their colors, new colors have to be calibrated
+    fn save_palette_editor(&mut self, ctx: &mut EventCtx) -> Result<(), BotError> {
+        let palette = self
+            .palette_editor
+            .iter()
+            .map(|entry| {
+                parse_hex_color(&entry.hex).ok_or_else(|| BotError::InvalidHexColor {
+                    index: entry.index,
+                    value: entry.hex.clone(),
+                })
+            })
+            .collect::<Result<Vec<Rgb<u8>>, BotError>>()?;
+        if palette.is_empty() {
+            return Err(BotError::EmptyPalette);
+        }
+
+        self.colors_pos = self
+            .palette_editor
+            .iter()
+            .map(|entry| entry.position)
+            .collect();
+        let columns = self.profile.layout.columns.max(1);
+        self.profile.layout.rows = (palette.len() as u32).div_ceil(columns);
+        self.palette = Arc::new(palette);
+        self.close_palette_editor(ctx);
+        self.save_profile(ctx.window())?;
+
+        // The loaded image was quantized for the previous palette
+        if let Some(path) = self.image_path.clone() {
+            self.get_image(ctx, path)?;
+        }
+        Ok(())
+    }
+
+    fn save_profile(&mut self, window: &WindowHandle) -> Result<(), BotError> {
+        let display_size = Screen::get_display_rect().size();
+        self.profile.screen = Some(ScreenInfo {
+            width: display_size.width,
+            height: display_size.height,
+            scale_factor: window.get_scale().map_or(1.0, |scale| scale.x()),
+        });
+        self.profile.palette =
+            Profile::new(&self.profile.name, &self.palette, self.profile.layout).palette;
+        // Calibrations follow the canvas unless the user picked another anchor
+        if let (None, Some(area)) = (self.profile.anchor, self.area()) {
+            self.profile.set_anchor(area.origin().into());
+        }
+        self.profile.set_colors_pos(&self.colors_pos);
+        self.profile.set_canvas_rect(self.area());
+
+        self.profile.save()
+    }
+
+    fn select_profile(&mut self, ctx: &mut EventCtx, name: &str) -> Result<(), BotError> {
+        let Some(template) = builtin_profile(name) else {
+            return Ok(());
+        };
+        let profile = load_or_migrate_profile(template)?;
+        save_selected_profile_name(&profile.name)?;
+        self.apply_profile(profile);
+
+        // The loaded image was quantized for the previous palette
+        if let Some(path) = self.image_path.clone() {
+            self.get_image(ctx, path)?;
+        }
+        Ok(())
+    }
+
+    fn apply_profile(&mut self, profile: Profile) {
+        let canvas = profile.canvas_rect();
+        self.palette = Arc::new(profile.colors());
+        self.colors_pos = profile.colors_pos();
+        self.start_pos = canvas.map(|canvas| canvas.origin().into());
+        self.end_pos = canvas.map(|canvas| (canvas.x1, canvas.y1));
+        self.selected_profile = profile.name.clone();
+        self.profile = profile;
+    }
+
+    // Draws from the checkpoint if `resume` is set, from the beginning otherwise
+    fn draw_image(&mut self, ctx: &mut EventCtx, resume: bool) -> Result<(), BotError> {
+        if self.drawing {
+            return Ok(());
+        }
+        let plan = self.build_plan();
+        let checkpoint = match self.checkpoint.filter(|_| resume) {
+            Some(checkpoint) if !checkpoint.matches(&plan) => {
+                return Err(BotError::CheckpointMismatch)
+            }
+            Some(checkpoint) => checkpoint,
+            None => Checkpoint::start(&plan),
+        };
+        // Clearing pixels uses the eraser when its button is known
+        let eraser = self.profile.tool(ERASER_TOOL);
+        let used_colors: Vec<Rgb<u8>> = plan
+            .colors
+            .iter()
+            .map(|strokes| strokes.color)
+            .filter(|color| eraser.is_none() || *color != BACKGROUND)
+            .collect();
+        let window = ctx.window();
+        let mut windows = vec![Rect::from_origin_size(
+            window.get_position(),
+            window.get_size(),
+        )];
+        windows.extend(self.preview_window);
+
+        let checklist = preflight(&PreflightInput {
+            area: self.area(),
+            image_area: self.image_area,
+            image_size: self.current_image.dimensions(),
+            used_colors: &used_colors,
+            palette: &self.palette,
+            colors_pos: &self.colors_pos,
+            windows: &windows,
+        });
+        if !checklist.passed() {
+            return Err(BotError::Preflight(checklist));
+        }
+        // The plan is relative to the top left corner, not to where the drag started
+        let start_pos = self
+            .area()
+            .ok_or(BotError::AreaNotSelected)?
+            .origin()
+            .into();
+        if !resume {
+            clear_checkpoint()?;
+            self.checkpoint = None;
+        }
+
         let palette = Arc::clone(&self.palette);
-        let colors_coordinates = (*palette)
+        let mut colors_coordinates: HashMap<Rgb<u8>, (f64, f64)> = (*palette)
             .clone()
             .into_iter()
-            .zip(self.colors_pos.clone().into_iter())
-            // .map(|(color, (x, y))| (color, (x as u32, y as u32)))
+            .zip(self.colors_pos.clone())
+            .filter_map(|(color, pos)| Some((color, pos?)))
             .collect();
+        if let Some(eraser) = eraser {
+            colors_coordinates.insert(BACKGROUND, eraser);
+        }
 
-        draw_image(
-            &self.current_image,
-            &colors_coordinates,
-            &self.start_pos.unwrap(),
-            self.pixel_interval as u8,
+        let area_size = self
+            .area()
+            .map_or((0, 0), |area| (area.width() as u32, area.height() as u32));
+        let verification = self
+            .verify_screenshot
+            .clone()
+            .zip(self.area_in_screenshot(ctx.window()))
+            .filter(|_| self.verify_rounds >= 1.0)
+            .map(|(path, rect)| Verification {
+                source: Box::new(FileScreenSource::watch(path, rect, area_size)),
+                expected: self.simulated_image.clone(),
+                rounds: self.verify_rounds as u32,
+            });
+
+        let hotkeys = self.hotkeys.subscribe();
+        let screen = ScreenSpace::current(ctx.window());
+        let fail_safe = FailSafe::new(
+            self.fail_safe.clone(),
+            screen.physical_bounds(),
+            self.hotkeys.subscribe_pointer(),
         );
+        let humanize = self.humanize.clone();
+        let event_sink = ctx.get_external_handle();
+
+        self.drawing = true;
+        self.progress = None;
+        self.status = String::from("Drawing...");
+
+        thread::spawn(move || {
+            let progress_sink = event_sink.clone();
+            let outcome = draw_image(
+                DrawJob {
+                    plan,
+                    colors_coordinates,
+                    start_pos,
+                    checkpoint,
+                    verification,
+                    humanize,
+                    screen,
+                },
+                hotkeys,
+                fail_safe,
+                move |progress| {
+                    let _ = progress_sink.submit_command(DRAW_PROGRESS, progress, Target::Auto);
+                },
+            );
+            let _ = event_sink.submit_command(DRAW_FINISHED, outcome, Target::Auto);
+        });
+        Ok(())
     }
 }
 
 fn main() {
-    let mut x_offset = 0.0;
-    let mut y_offset = 0.0;
-
-    let monitors = Screen::get_monitors();
-
-    for monitor in &monitors {
-        if monitor.is_primary() {
-            let work_rect = monitor.virtual_work_rect();
-
-            x_offset = work_rect.x0;
-            y_offset = work_rect.y0;
-        }
-    }
-
-    // Загружаем позиции цветов из файла
-    let loaded_colors_pos = load_colors_pos("colors_pos.txt").unwrap_or(vec![]).clone();
-
-    let initial_state = AppState {
-        palette: Arc::new(vec![
-            Rgb([0, 0, 0]),
-            Rgb([102, 102, 102]),
-            Rgb([0, 80, 205]),
-            Rgb([255, 255, 255]),
-            Rgb([170, 170, 170]),
-            Rgb([38, 201, 255]),
-            Rgb([1, 116, 32]),
-            Rgb([153, 0, 0]),
-            Rgb([150, 65, 18]),
-            Rgb([17, 176, 60]),
-            Rgb([255, 0, 19]),
-            Rgb([255, 120, 41]),
-            Rgb([176, 112, 28]),
-            Rgb([153, 0, 78]),
-            Rgb([203, 90, 87]),
-            Rgb([255, 193, 38]),
-            Rgb([255, 0, 143]),
-            Rgb([254, 175, 168]),
-        ]),
-        colors_pos: loaded_colors_pos,
+    // Any argument means the command line instead of the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
+    let hotkeys = HotkeyService::start(load_hotkey_bindings("hotkeys.txt").unwrap_or_default());
+
+    // Загружаем профиль калибровки из файла
+    let template = load_selected_profile_name()
+        .and_then(|name| builtin_profile(&name))
+        .or_else(|| builtin_profile(GARTIC_PHONE))
+        .expect("Gartic Phone is a built-in profile");
+    let mut status = String::new();
+    let profile = load_or_migrate_profile(template.clone()).unwrap_or_else(|e| {
+        status = e.to_string();
+        template
+    });
+
+    let mut initial_state = AppState {
+        palette: Arc::new(Vec::new()),
+        colors_pos: Vec::new(),
+        palette_mismatches: Vec::new(),
+        palette_target: None,
+        palette_single: false,
+        recalibrate_color: String::new(),
+        source_image: ImageBuffer::new(1, 1),
         current_image: ImageBuffer::new(1, 1),
+        image_area: None,
+        simulated_image: ImageBuffer::new(1, 1),
+        preview_stats: String::new(),
+        canvas_image: None,
+        estimated_time: None,
         sub_window: None,
+        preview_window: None,
+        image_path: None,
+        loading_image: false,
+        image_generation: 0,
+        hotkeys: hotkeys.clone(),
         dithering: true,
         pixel_interval: 2.0,
+        brush_width: 2.0,
+        fail_safe: FailSafeConfig::default(),
+        humanize: HumanizeConfig::default(),
+        drawing: false,
+        verify_screenshot: None,
+        verify_rounds: 0.0,
+        checkpoint: load_checkpoint(),
+        progress: None,
+        status,
         view_status: ViewStatus::Default,
         start_pos: None,
         end_pos: None,
-        x_offset,
-        y_offset,
+        lock_aspect: false,
+        selected_profile: String::new(),
+        profile: profile.clone(),
+        palette_editor: Arc::new(Vec::new()),
+        editor_screenshot: None,
     };
 
+    initial_state.apply_profile(profile);
+
     let main_window = WindowDesc::new(build_root_widget())
         .title(LocalizedString::new("Drawing Bot"))
         .transparent(true)
-        .window_size((300.0, 300.0));
+        .window_size((320.0, 760.0));
+
+    let launcher = AppLauncher::with_window(main_window);
+
+    let event_sink = launcher.get_external_handle();
+    let hotkey_actions = hotkeys.subscribe();
+    thread::spawn(move || {
+        for action in hotkey_actions {
+            if event_sink
+                .submit_command(HOTKEY_ACTION, action, Target::Auto)
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
 
-    AppLauncher::with_window(main_window)
+    launcher
         .log_to_console()
         .launch(initial_state)
         .expect("Failed to launch application");
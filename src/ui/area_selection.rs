@@ -0,0 +1,231 @@
+use druid::{Point, Rect, Vec2};
+
+// Distance from an edge of the area within which it can be dragged
+const HANDLE_SIZE: f64 = 8.0;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Edges {
+    pub left: bool,
+    pub top: bool,
+    pub right: bool,
+    pub bottom: bool,
+}
+
+impl Edges {
+    fn any(&self) -> bool {
+        self.left || self.top || self.right || self.bottom
+    }
+}
+
+// What a mouse drag in the area overlay does, decided when the button is pressed
+#[derive(Copy, Clone, Debug)]
+pub enum AreaDrag {
+    Select { anchor: Point },
+    Move { grab: Point, area: Rect },
+    Resize { edges: Edges, area: Rect },
+}
+
+impl AreaDrag {
+    pub fn start(area: Option<Rect>, pos: Point) -> AreaDrag {
+        if let Some(area) = area {
+            let near = |a: f64, b: f64| (a - b).abs() <= HANDLE_SIZE;
+            let within_x = pos.x >= area.x0 - HANDLE_SIZE && pos.x <= area.x1 + HANDLE_SIZE;
+            let within_y = pos.y >= area.y0 - HANDLE_SIZE && pos.y <= area.y1 + HANDLE_SIZE;
+            let edges = Edges {
+                left: within_y && near(pos.x, area.x0),
+                top: within_x && near(pos.y, area.y0),
+                right: within_y && near(pos.x, area.x1),
+                bottom: within_x && near(pos.y, area.y1),
+            };
+            if edges.any() {
+                return AreaDrag::Resize { edges, area };
+            }
+            if area.contains(pos) {
+                return AreaDrag::Move { grab: pos, area };
+            }
+        }
+        AreaDrag::Select { anchor: pos }
+    }
+
+    // The area for the current pointer position. `aspect` is the width to
+    // height ratio to keep, if locked
+    pub fn update(&self, pos: Point, aspect: Option<f64>) -> Rect {
+        match *self {
+            AreaDrag::Select { anchor } => from_anchor(anchor, pos, aspect),
+            AreaDrag::Move { grab, area } => area + (pos - grab),
+            AreaDrag::Resize { edges, area } => {
+                let fixed_x = if edges.left { area.x1 } else { area.x0 };
+                let fixed_y = if edges.top { area.y1 } else { area.y0 };
+                let horizontal = edges.left || edges.right;
+                let vertical = edges.top || edges.bottom;
+                match (aspect, horizontal, vertical) {
+                    (Some(_), true, true) => from_anchor(Point::new(fixed_x, fixed_y), pos, aspect),
+                    (Some(aspect), true, false) => {
+                        let height = (pos.x - fixed_x).abs() / aspect;
+                        Rect::new(fixed_x, area.y0, pos.x, area.y0 + height).abs()
+                    }
+                    (Some(aspect), false, true) => {
+                        let width = (pos.y - fixed_y).abs() * aspect;
+                        Rect::new(area.x0, fixed_y, area.x0 + width, pos.y).abs()
+                    }
+                    _ => {
+                        let mut resized = area;
+                        if edges.left {
+                            resized.x0 = pos.x;
+                        }
+                        if edges.right {
+                            resized.x1 = pos.x;
+                        }
+                        if edges.top {
+                            resized.y0 = pos.y;
+                        }
+                        if edges.bottom {
+                            resized.y1 = pos.y;
+                        }
+                        // Dragging an edge past the opposite one flips the area
+                        resized.abs()
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Rectangle between a fixed corner and the pointer, shrunk to the aspect ratio
+fn from_anchor(anchor: Point, pos: Point, aspect: Option<f64>) -> Rect {
+    let Some(aspect) = aspect else {
+        return Rect::from_points(anchor, pos);
+    };
+    let delta = pos - anchor;
+    let (mut width, mut height) = (delta.x.abs(), delta.y.abs());
+    if width > height * aspect {
+        width = height * aspect;
+    } else {
+        height = width / aspect;
+    }
+    Rect::from_points(
+        anchor,
+        anchor + Vec2::new(width * delta.x.signum(), height * delta.y.signum()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: Rect = Rect::new(100.0, 100.0, 300.0, 200.0);
+
+    fn drag(from: (f64, f64), to: (f64, f64), aspect: Option<f64>) -> Rect {
+        AreaDrag::start(Some(AREA), from.into()).update(to.into(), aspect)
+    }
+
+    #[test]
+    fn backwards_drag_selects_a_positive_area() {
+        let drag = AreaDrag::start(None, Point::new(300.0, 200.0));
+        let area = drag.update(Point::new(100.0, 50.0), None);
+        assert_eq!(area, Rect::new(100.0, 50.0, 300.0, 200.0));
+        assert!(area.width() > 0.0 && area.height() > 0.0);
+    }
+
+    #[test]
+    fn every_handle_resizes_its_edges() {
+        let cases = [
+            (
+                (100.0, 150.0),
+                (50.0, 170.0),
+                Rect::new(50.0, 100.0, 300.0, 200.0),
+            ),
+            (
+                (300.0, 150.0),
+                (350.0, 170.0),
+                Rect::new(100.0, 100.0, 350.0, 200.0),
+            ),
+            (
+                (200.0, 100.0),
+                (220.0, 80.0),
+                Rect::new(100.0, 80.0, 300.0, 200.0),
+            ),
+            (
+                (200.0, 200.0),
+                (220.0, 250.0),
+                Rect::new(100.0, 100.0, 300.0, 250.0),
+            ),
+            (
+                (100.0, 100.0),
+                (90.0, 80.0),
+                Rect::new(90.0, 80.0, 300.0, 200.0),
+            ),
+            (
+                (300.0, 100.0),
+                (320.0, 80.0),
+                Rect::new(100.0, 80.0, 320.0, 200.0),
+            ),
+            (
+                (100.0, 200.0),
+                (90.0, 220.0),
+                Rect::new(90.0, 100.0, 300.0, 220.0),
+            ),
+            (
+                (300.0, 200.0),
+                (320.0, 220.0),
+                Rect::new(100.0, 100.0, 320.0, 220.0),
+            ),
+        ];
+        for (from, to, expected) in cases {
+            assert_eq!(drag(from, to, None), expected, "handle at {:?}", from);
+        }
+    }
+
+    #[test]
+    fn handles_are_grabbed_near_the_edge() {
+        // A few pixels off the edge still resizes, further inside moves
+        assert_eq!(
+            drag((105.0, 150.0), (50.0, 150.0), None),
+            Rect::new(50.0, 100.0, 300.0, 200.0)
+        );
+        assert_eq!(
+            drag((200.0, 150.0), (210.0, 160.0), None),
+            Rect::new(110.0, 110.0, 310.0, 210.0)
+        );
+    }
+
+    #[test]
+    fn edge_dragged_past_the_opposite_one_flips() {
+        assert_eq!(
+            drag((100.0, 150.0), (400.0, 150.0), None),
+            Rect::new(300.0, 100.0, 400.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn locked_aspect_ratio_is_kept() {
+        let select = AreaDrag::start(None, Point::ZERO).update(Point::new(100.0, 100.0), Some(2.0));
+        assert_eq!(select, Rect::new(0.0, 0.0, 100.0, 50.0));
+
+        let cases = [
+            // Right edge, the height follows the width
+            (
+                (300.0, 150.0),
+                (400.0, 150.0),
+                Rect::new(100.0, 100.0, 400.0, 250.0),
+            ),
+            // Bottom edge, the width follows the height
+            (
+                (200.0, 200.0),
+                (200.0, 300.0),
+                Rect::new(100.0, 100.0, 500.0, 300.0),
+            ),
+            // Corner, shrunk to the ratio
+            (
+                (300.0, 200.0),
+                (500.0, 260.0),
+                Rect::new(100.0, 100.0, 420.0, 260.0),
+            ),
+        ];
+        for (from, to, expected) in cases {
+            let area = drag(from, to, Some(2.0));
+            assert_eq!(area, expected, "handle at {:?}", from);
+            assert_eq!(area.width() / area.height(), 2.0);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::PathBuf;
+
+use druid::image::{ImageBuffer, Rgb};
+use druid::{Selector, SingleUse};
+
+use crate::error::BotError;
+use crate::utils::hotkeys::HotkeyAction;
+use crate::utils::image_drawing::{DrawOutcome, DrawProgress};
+
+pub const HOTKEY_ACTION: Selector<HotkeyAction> = Selector::new("drawing-bot.hotkey-action");
+pub const DRAW_PROGRESS: Selector<DrawProgress> = Selector::new("drawing-bot.draw-progress");
+pub const DRAW_FINISHED: Selector<DrawOutcome> = Selector::new("drawing-bot.draw-finished");
+pub const PALETTE_EDIT: Selector<PaletteEdit> = Selector::new("drawing-bot.palette-edit");
+pub const IMAGE_LOADED: Selector<SingleUse<LoadedImage>> =
+    Selector::new("drawing-bot.image-loaded");
+pub const PREPARE_IMAGE: Selector = Selector::new("drawing-bot.prepare-image");
+pub const UPDATE_SIMULATION: Selector = Selector::new("drawing-bot.update-simulation");
+
+// Sent by the rows of the palette editor, the index is the row's position
+#[derive(Copy, Clone, Debug)]
+pub enum PaletteEdit {
+    MoveUp(usize),
+    MoveDown(usize),
+    Remove(usize),
+}
+
+pub struct LoadedImage {
+    pub path: PathBuf,
+    pub generation: u64,
+    pub image: Result<PreparedImage, BotError>,
+}
+
+// Both images are already fitted into the drawing area
+pub struct PreparedImage {
+    pub source: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    pub quantized: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    pub area_size: (u32, u32),
+}
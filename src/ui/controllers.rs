@@ -1,18 +1,62 @@
-use druid::{widget::Controller, EventCtx, Widget};
-use druid::{Env, Event};
+use druid::keyboard_types::Key;
+use druid::{widget::Controller, Data, EventCtx, LifeCycle, LifeCycleCtx, UpdateCtx, Widget};
+use druid::{Env, Event, Rect, Vec2};
 
-use crate::utils::save_coordinates::save_colors_pos;
+use crate::error::BotError;
+use crate::utils::checkpoint::load_checkpoint;
+use crate::utils::hotkeys::HotkeyAction;
+use crate::utils::image_drawing::DrawOutcome;
 use crate::AppState;
 
+use super::area_selection::AreaDrag;
+use super::commands::{
+    DRAW_FINISHED, DRAW_PROGRESS, HOTKEY_ACTION, IMAGE_LOADED, PALETTE_EDIT, PREPARE_IMAGE,
+    UPDATE_SIMULATION,
+};
+use super::dialogs::{confirm, show_error};
 use super::ui::{Mode, ViewStatus};
 
+// Clicks this close to a palette marker pick it up
+const MARKER_RADIUS: f64 = 12.0;
+
 pub struct PainterController {
     mode: Mode,
+    drag: Option<AreaDrag>,
+    // Palette placements as (index, previous position), for undo
+    placed: Vec<(usize, Option<(f64, f64)>)>,
 }
 
 impl PainterController {
     pub fn new(mode: Mode) -> PainterController {
-        PainterController { mode }
+        PainterController {
+            mode,
+            drag: None,
+            placed: Vec::new(),
+        }
+    }
+}
+
+fn finish_area(ctx: &mut EventCtx, data: &mut AppState) {
+    data.view_status = ViewStatus::Default;
+    data.switch_view_to_default(ctx.window());
+    if let Err(e) = data.save_profile(ctx.window()) {
+        show_error("Failed to save profile", &e.to_string());
+    }
+}
+
+fn finish_palette(ctx: &mut EventCtx, data: &mut AppState) {
+    data.view_status = ViewStatus::Default;
+    data.switch_view_to_default(ctx.window());
+    data.palette_target = None;
+    data.palette_single = false;
+    if let Err(e) = data.save_profile(ctx.window()) {
+        show_error(
+            "Failed to save profile",
+            &format!(
+                "{}\n\nThe palette will have to be selected again next time.",
+                e
+            ),
+        );
     }
 }
 
@@ -27,57 +71,482 @@ impl<W: Widget<AppState>> Controller<AppState, W> for PainterController {
     ) {
         match self.mode {
             Mode::Area => match event {
+                // The overlay has no focus when it opens, arrow keys wouldn't reach it
+                Event::MouseMove(_) if !ctx.is_active() && !ctx.has_focus() => ctx.request_focus(),
                 Event::MouseDown(me) => {
                     if me.buttons.has_right() {
-                        data.view_status = ViewStatus::Default;
-                        data.switch_view_to_default(ctx.window());
+                        finish_area(ctx, data);
                         return;
                     }
                     ctx.set_active(true);
-                    let pos: (f64, f64) = ctx.to_screen(me.pos).into();
-                    data.start_pos = Some(pos);
+                    ctx.request_focus();
+                    self.drag = Some(AreaDrag::start(data.area(), ctx.to_screen(me.pos)));
                 }
                 Event::MouseMove(me) if ctx.is_active() && me.buttons.has_left() => {
-                    if data.start_pos.is_some() {
-                        let pos: (f64, f64) = ctx.to_screen(me.pos).into();
-                        data.end_pos = Some(pos);
+                    if let Some(drag) = self.drag {
+                        data.set_area(drag.update(ctx.to_screen(me.pos), data.area_aspect()));
                     }
                 }
-                Event::MouseUp(_me) if ctx.is_active() => ctx.set_active(false),
+                Event::MouseUp(_me) if ctx.is_active() => {
+                    ctx.set_active(false);
+                    self.drag = None;
+                }
+                Event::KeyDown(key) => {
+                    let step = if key.mods.shift() { 10.0 } else { 1.0 };
+                    let nudge = match key.key {
+                        Key::ArrowLeft => Vec2::new(-step, 0.0),
+                        Key::ArrowRight => Vec2::new(step, 0.0),
+                        Key::ArrowUp => Vec2::new(0.0, -step),
+                        Key::ArrowDown => Vec2::new(0.0, step),
+                        Key::Enter => {
+                            finish_area(ctx, data);
+                            return;
+                        }
+                        _ => Vec2::ZERO,
+                    };
+                    if let Some(area) = data.area() {
+                        data.set_area(area + nudge);
+                    }
+                    ctx.set_handled();
+                }
                 _ => (),
             },
             Mode::Palette => match event {
+                // Backspace needs the focus, like the arrow keys of the area overlay
+                Event::MouseMove(_) if !ctx.has_focus() => ctx.request_focus(),
                 Event::MouseDown(me) => {
-                    if me.buttons.has_right() || data.colors_pos.len() >= 18 {
-                        data.view_status = ViewStatus::Default;
-                        data.switch_view_to_default(ctx.window());
-                        save_colors_pos("colors_pos.txt", &data.colors_pos).unwrap();
+                    if me.buttons.has_right() {
+                        finish_palette(ctx, data);
                         return;
                     }
-                    ctx.set_active(true);
                     let pos: (f64, f64) = ctx.to_screen(me.pos).into();
-                    data.colors_pos.push(pos);
+                    // A click on a placed marker picks it up, the next click puts it down
+                    let marker = data.colors_pos.iter().position(|placed| {
+                        placed.is_some_and(|placed| {
+                            (placed.0 - pos.0).hypot(placed.1 - pos.1) <= MARKER_RADIUS
+                        })
+                    });
+                    if let (None, Some(index)) = (data.palette_target, marker) {
+                        data.palette_target = Some(index);
+                        return;
+                    }
+                    if let Some(index) = data.next_palette_slot() {
+                        self.placed.push((index, data.colors_pos[index]));
+                        data.colors_pos[index] = Some(pos);
+                        data.palette_target = None;
+                    }
+                    // Done once every color of the profile's palette has a position
+                    if data.palette_single || data.next_palette_slot().is_none() {
+                        finish_palette(ctx, data);
+                        return;
+                    }
+                    ctx.set_active(true);
+                }
+                Event::KeyDown(key)
+                    if key.key == Key::Backspace
+                        || (key.mods.ctrl() && key.key == Key::Character(String::from("z"))) =>
+                {
+                    // A picked up marker is put back before placements are undone
+                    if data.palette_target.is_some() && !data.palette_single {
+                        data.palette_target = None;
+                    } else if let Some((index, previous)) = self.placed.pop() {
+                        data.colors_pos[index] = previous;
+                    }
+                    ctx.set_handled();
                 }
                 _ => (),
             },
+            Mode::PaletteVerify => {
+                if let Event::MouseDown(me) = event {
+                    if me.buttons.has_right() {
+                        data.palette_mismatches.clear();
+                        data.view_status = ViewStatus::Default;
+                        data.switch_view_to_default(ctx.window());
+                    } else {
+                        data.reclick_mismatches();
+                    }
+                    return;
+                }
+            }
+            Mode::Anchor | Mode::Reanchor | Mode::Eraser => {
+                if let Event::MouseDown(me) = event {
+                    data.view_status = ViewStatus::Default;
+                    data.switch_view_to_default(ctx.window());
+                    if !me.buttons.has_right() {
+                        let pos: (f64, f64) = ctx.to_screen(me.pos).into();
+                        let result = match self.mode {
+                            Mode::Anchor => data.set_anchor(ctx.window(), pos),
+                            Mode::Eraser => data.set_eraser(ctx.window(), pos),
+                            _ => data.move_anchor(ctx.window(), pos),
+                        };
+                        if let Err(e) = result {
+                            show_error("Failed to save profile", &e.to_string());
+                        }
+                    }
+                    return;
+                }
+            }
+            Mode::PaletteConfirm => {
+                if let Event::MouseDown(me) = event {
+                    data.view_status = ViewStatus::Default;
+                    data.switch_view_to_default(ctx.window());
+                    if me.buttons.has_right() {
+                        // Rejected, go back to the saved calibration
+                        data.colors_pos = data.profile.colors_pos();
+                        data.status = String::from("Detected palette discarded");
+                    } else if let Err(e) = data.save_profile(ctx.window()) {
+                        show_error("Failed to save profile", &e.to_string());
+                    }
+                    return;
+                }
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn lifecycle(
+        &mut self,
+        child: &mut W,
+        ctx: &mut LifeCycleCtx,
+        event: &LifeCycle,
+        data: &AppState,
+        env: &Env,
+    ) {
+        // Arrow keys nudge the area, backspace undoes palette clicks
+        if let (LifeCycle::WidgetAdded, Mode::Area | Mode::Palette) = (event, &self.mode) {
+            ctx.register_for_focus();
+        }
+        child.lifecycle(ctx, event, data, env)
+    }
+}
+
+#[derive(Default)]
+pub struct PreviewWindowController {
+    rect: Option<Rect>,
+}
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PreviewWindowController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::WindowConnected | Event::WindowSize(_) | Event::MouseMove(_) = event {
+            // Remember where the preview is, it must not cover the drawing area
+            let window = ctx.window();
+            let rect = Rect::from_origin_size(window.get_position(), window.get_size());
+            self.rect = Some(rect);
+            if data.preview_window != Some(rect) {
+                data.preview_window = Some(rect);
+            }
+        }
+        // A newer preview may already have replaced this one
+        if let Event::WindowDisconnected = event {
+            if data.preview_window == self.rect {
+                data.preview_window = None;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+// Size of the selected area the image has to be fitted into, once it is no
+// longer being edited
+fn settled_area_size(data: &AppState) -> Option<(u32, u32)> {
+    if data.view_status != ViewStatus::Default {
+        return None;
+    }
+    data.area()
+        .map(|area| (area.width() as u32, area.height() as u32))
+}
+
+// Keeps the loaded image, the simulated result and the time estimate in line
+// with everything they depend on. Changes can come from any window, so they are
+// noticed in update and handled as commands to this widget
+pub struct PlanSettingsController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PlanSettingsController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if cmd.is(PREPARE_IMAGE) {
+                if let Some(path) = data.image_path.clone() {
+                    if let Err(e) = data.get_image(ctx, path) {
+                        show_error("Failed to load image", &e.to_string());
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
+            if cmd.is(UPDATE_SIMULATION) {
+                data.update_simulation();
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+
+    fn update(
+        &mut self,
+        child: &mut W,
+        ctx: &mut UpdateCtx,
+        old_data: &AppState,
+        data: &AppState,
+        env: &Env,
+    ) {
+        // Closing the area overlay with a new size makes the image stale
+        let area = settled_area_size(data);
+        let area_changed =
+            area != settled_area_size(old_data) && area.is_some() && area != data.image_area;
+        let idle = data.image_path.is_some() && !data.loading_image && !data.drawing;
+        if idle && (area_changed || data.dithering != old_data.dithering) {
+            ctx.submit_command(PREPARE_IMAGE.to(ctx.widget_id()));
+        } else if (data.pixel_interval, data.brush_width)
+            != (old_data.pixel_interval, old_data.brush_width)
+            || !data.humanize.same(&old_data.humanize)
+        {
+            ctx.submit_command(UPDATE_SIMULATION.to(ctx.widget_id()));
+        }
+        child.update(ctx, old_data, data, env)
+    }
+}
+
+// The start hotkey resumes when it can. A checkpoint of another image or
+// other settings is dropped once the user agrees
+fn draw_or_resume(ctx: &mut EventCtx, data: &mut AppState) -> Result<(), BotError> {
+    match data.checkpoint {
+        Some(checkpoint) if !checkpoint.matches(&data.build_plan()) => {
+            if confirm(
+                "Drawing Bot",
+                "The interrupted drawing can't be resumed, the image or the drawing settings changed since. Draw the image from the beginning?",
+            ) {
+                data.draw_image(ctx, false)
+            } else {
+                Ok(())
+            }
+        }
+        checkpoint => data.draw_image(ctx, checkpoint.is_some()),
+    }
+}
+
+pub struct HotkeyController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for HotkeyController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if let Some(action) = cmd.get(HOTKEY_ACTION) {
+                // Pause and Abort are handled by the drawing executor itself.
+                // The others would move the overlay or the image under the bot
+                if data.view_status == ViewStatus::Default && !data.drawing {
+                    let result = match action {
+                        HotkeyAction::StartDraw => draw_or_resume(ctx, data),
+                        HotkeyAction::ReselectArea => {
+                            data.get_area(ctx);
+                            Ok(())
+                        }
+                        HotkeyAction::LoadNextImage => data.load_next_image(ctx),
+                        HotkeyAction::Pause | HotkeyAction::Abort => Ok(()),
+                    };
+                    if let Err(e) = result {
+                        show_error("Drawing Bot", &e.to_string());
+                    }
+                }
+                ctx.set_handled();
+                return;
+            }
         }
         child.event(ctx, event, data, env)
     }
 }
 
-pub struct DragController;
+pub struct DrawingController;
 
-impl<T, W: Widget<T>> Controller<T, W> for DragController {
+impl<W: Widget<AppState>> Controller<AppState, W> for DrawingController {
     fn event(
         &mut self,
-        _child: &mut W,
+        child: &mut W,
         ctx: &mut EventCtx,
         event: &Event,
-        _data: &mut T,
-        _env: &Env,
+        data: &mut AppState,
+        env: &Env,
     ) {
-        if let Event::MouseMove(_) = event {
-            ctx.window().handle_titlebar(true);
+        if let Event::Command(cmd) = event {
+            if let Some(progress) = cmd.get(DRAW_PROGRESS) {
+                data.status = progress.to_string();
+                data.progress = Some(progress.clone());
+                ctx.set_handled();
+                return;
+            }
+            if let Some(outcome) = cmd.get(DRAW_FINISHED) {
+                data.drawing = false;
+                data.checkpoint = load_checkpoint();
+                data.status = outcome.to_string();
+                match outcome {
+                    DrawOutcome::Completed { timings, .. } => {
+                        data.profile.timings = Some(*timings);
+                        data.update_simulation();
+                        if let Err(e) = data.profile.save() {
+                            show_error("Failed to save profile", &e.to_string());
+                        }
+                    }
+                    DrawOutcome::Failed(e) => show_error("Drawing failed", &e.to_string()),
+                    _ => (),
+                }
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+pub struct ImageLoadController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ImageLoadController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if let Some(loaded) = cmd.get(IMAGE_LOADED).and_then(|loaded| loaded.take()) {
+                ctx.set_handled();
+                // A newer load was started while this one was running, e.g. for a new palette
+                if loaded.generation != data.image_generation {
+                    return;
+                }
+                data.loading_image = false;
+                match loaded.image {
+                    Ok(image) => {
+                        data.source_image = image.source;
+                        data.current_image = image.quantized;
+                        data.image_area = Some(image.area_size);
+                        data.image_path = Some(loaded.path);
+                        data.update_simulation();
+                        data.show_image(ctx, &data.clone(), env);
+                    }
+                    Err(e) => show_error(
+                        "Failed to load image",
+                        &format!("{}\n\n{}", loaded.path.display(), e),
+                    ),
+                }
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+pub struct ProfilePickerController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ProfilePickerController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        let previous = data.selected_profile.clone();
+        child.event(ctx, event, data, env);
+
+        if data.selected_profile != previous {
+            let selected = data.selected_profile.clone();
+            if let Err(e) = data.select_profile(ctx, &selected) {
+                data.selected_profile = previous;
+                show_error("Failed to load profile", &e.to_string());
+            }
+        }
+    }
+}
+
+pub struct PaletteEditorController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for PaletteEditorController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let Event::Command(cmd) = event {
+            if let Some(edit) = cmd.get(PALETTE_EDIT) {
+                data.edit_palette(*edit);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+// Adds the color of the clicked screenshot pixel to the palette editor
+pub struct ScreenshotSampleController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for ScreenshotSampleController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        if let (Event::MouseDown(me), Some(screenshot)) = (event, &data.editor_screenshot) {
+            let (x, y) = (me.pos.x as u32, me.pos.y as u32);
+            if x < screenshot.width() && y < screenshot.height() {
+                let color = *screenshot.get_pixel(x, y);
+                data.add_palette_color(color);
+                ctx.set_handled();
+                return;
+            }
+        }
+        child.event(ctx, event, data, env)
+    }
+}
+
+// Saves the area when it is changed in the numeric panel
+pub struct AreaPanelController;
+
+impl<W: Widget<AppState>> Controller<AppState, W> for AreaPanelController {
+    fn event(
+        &mut self,
+        child: &mut W,
+        ctx: &mut EventCtx,
+        event: &Event,
+        data: &mut AppState,
+        env: &Env,
+    ) {
+        let previous = data.area();
+        child.event(ctx, event, data, env);
+
+        if data.area() != previous {
+            if let Err(e) = data.save_profile(ctx.window()) {
+                show_error("Failed to save profile", &e.to_string());
+            }
         }
     }
 }
@@ -1,2 +1,7 @@
+pub mod area_selection;
+pub mod commands;
 pub mod controllers;
+pub mod dialogs;
+#[allow(clippy::module_inception)]
 pub mod ui;
+pub mod zoom_image;
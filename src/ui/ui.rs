@@ -1,67 +1,331 @@
+use druid::image::Rgb;
+use druid::kurbo::Circle;
+use druid::piet::ImageFormat;
 use druid::piet::{FontFamily, Text, TextLayout, TextLayoutBuilder};
+use druid::text::ParseFormatter;
 use druid::widget::{
-    Button, Checkbox, Container, Flex, Label, Painter, SizedBox, Slider, ViewSwitcher,
+    Button, Checkbox, Container, CrossAxisAlignment, Either, FillStrat, Flex, Image, Label,
+    LineBreaking, List, Painter, ProgressBar, RadioGroup, Scroll, SizedBox, Slider, Spinner, Tabs,
+    TextBox, ViewSwitcher,
+};
+use druid::{
+    Color, Data, Env, EventCtx, ImageBuf, Lens, LensExt, Point, Rect, RenderContext, Screen, Vec2,
+    Widget, WidgetExt,
 };
-use druid::{Color, Data, Env, RenderContext, Screen, Widget, WidgetExt};
 
+use crate::utils::fail_safe::FailSafeConfig;
+use crate::utils::game_profiles::BUILTIN_PROFILE_NAMES;
+use crate::utils::hotkeys::HotkeyAction;
+use crate::utils::humanize::HumanizeConfig;
+use crate::utils::image_drawing::format_duration;
+use crate::utils::image_utils::parse_hex_color;
+use crate::utils::profile::ERASER_TOOL;
 use crate::AppState;
 
-use super::controllers::PainterController;
+use super::commands::{PaletteEdit, PALETTE_EDIT};
+use super::controllers::{
+    AreaPanelController, DrawingController, HotkeyController, ImageLoadController,
+    PainterController, PaletteEditorController, PlanSettingsController, ProfilePickerController,
+    ScreenshotSampleController,
+};
+use super::dialogs::show_error;
+use super::zoom_image::ZoomImage;
 
 #[derive(Copy, Clone, Data, PartialEq)]
 pub enum ViewStatus {
     Default,
     Area,
     Palette,
+    PaletteConfirm,
+    PaletteEditor,
+    PaletteVerify,
+    Anchor,
+    Reanchor,
+    Eraser,
+}
+
+// One row of the palette editor. `position` is the calibrated button of the
+// color and stays with it when the rows are reordered
+#[derive(Clone, Data, Lens)]
+pub struct PaletteEditorEntry {
+    pub index: usize,
+    pub hex: String,
+    pub position: Option<(f64, f64)>,
 }
 
 pub enum Mode {
     Area,
     Palette,
+    PaletteConfirm,
+    PaletteVerify,
+    Anchor,
+    Reanchor,
+    Eraser,
 }
 
 pub fn build_root_widget() -> impl Widget<AppState> {
     let view_switcher = ViewSwitcher::new(
         |data: &AppState, _env| data.view_status,
-        |_ctx, data, _env| match data.view_status {
+        |_ctx, data, _env| {
+            match data.view_status {
             ViewStatus::Default => Box::new(
                 Flex::column()
-                    .with_child(Button::new("Select area").on_click(
-                        |ctx, data: &mut AppState, _env| {
-                            // ctx.window().set_size((1920.0, 1080.0));
-                            data.get_area(ctx);
-                        },
-                    ))
+                    .with_child(
+                        RadioGroup::column(
+                            BUILTIN_PROFILE_NAMES
+                                .iter()
+                                .map(|&name| (name, name.to_string())),
+                        )
+                        .lens(AppState::selected_profile)
+                        .controller(ProfilePickerController),
+                    )
                     .with_spacer(10.0)
-                    .with_child(Button::new("Load Image").on_click(
-                        |_ctx, data: &mut AppState, _env| {
-                            data.save_image(_ctx, &data.clone(), _env);
-                        },
-                    ))
+                    .with_child(
+                        Flex::row()
+                            .with_child(Button::new("Select area").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    // ctx.window().set_size((1920.0, 1080.0));
+                                    data.get_area(ctx);
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(Button::new("Detect").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    if let Err(e) = data.detect_area(ctx) {
+                                        show_error("Failed to detect area", &e.to_string());
+                                    }
+                                },
+                            )),
+                    )
+                    .with_spacer(5.0)
+                    .with_child(build_area_panel())
                     .with_spacer(10.0)
-                    .with_child(Button::new("Select palette").on_click(
-                        |ctx, data: &mut AppState, _env| {
-                            data.get_palette(ctx);
-                        },
-                    ))
+                    .with_child(
+                        Flex::row()
+                            .with_child(
+                                Button::new("Load Image")
+                                    .on_click(|ctx, data: &mut AppState, _env| {
+                                        if let Err(e) = data.save_image(ctx) {
+                                            show_error("Failed to load image", &e.to_string());
+                                        }
+                                    })
+                                    .disabled_if(|data: &AppState, _env| data.loading_image),
+                            )
+                            .with_spacer(5.0)
+                            .with_child(Either::new(
+                                |data: &AppState, _env| data.loading_image,
+                                Spinner::new(),
+                                SizedBox::empty(),
+                            )),
+                    )
                     .with_spacer(10.0)
-                    .with_child(Button::new("Draw image").on_click(
-                        |_ctx, data: &mut AppState, _env| {
-                            data.draw_image();
-                        },
+                    .with_child(
+                        Flex::row()
+                            .with_child(Button::new("Select palette").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    data.get_palette(ctx);
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(Button::new("Detect").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    if let Err(e) = data.detect_palette(ctx) {
+                                        show_error("Failed to detect palette", &e.to_string());
+                                    }
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(Button::new("Edit palette").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    data.open_palette_editor(ctx);
+                                },
+                            )),
+                    )
+                    .with_spacer(5.0)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Button::new("Verify palette").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    if let Err(e) = data.verify_palette(ctx) {
+                                        show_error("Failed to verify palette", &e.to_string());
+                                    }
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(
+                                TextBox::new()
+                                    .with_placeholder("# or hex")
+                                    .fix_width(70.0)
+                                    .lens(AppState::recalibrate_color),
+                            )
+                            .with_child(Button::new("Recalibrate").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    if let Err(e) = data.recalibrate_palette_color(ctx) {
+                                        show_error("Failed to recalibrate color", &e.to_string());
+                                    }
+                                },
+                            )),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Button::new("Set anchor").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    data.get_point(ctx, ViewStatus::Anchor);
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(
+                                Button::new("Re-anchor")
+                                    .on_click(|ctx, data: &mut AppState, _env| {
+                                        data.get_point(ctx, ViewStatus::Reanchor);
+                                    })
+                                    .disabled_if(|data: &AppState, _env| {
+                                        data.profile.anchor.is_none()
+                                    }),
+                            )
+                            .with_spacer(5.0)
+                            .with_child(Button::new("Select eraser").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    data.get_point(ctx, ViewStatus::Eraser);
+                                },
+                            )),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Button::new("Canvas screenshot").on_click(
+                                |ctx, data: &mut AppState, _env| {
+                                    if let Err(e) = data.load_canvas_screenshot(ctx) {
+                                        show_error("Failed to load screenshot", &e.to_string());
+                                    }
+                                },
+                            ))
+                            .with_spacer(5.0)
+                            .with_child(
+                                Button::new("Clear")
+                                    .on_click(|_ctx, data: &mut AppState, _env| {
+                                        data.canvas_image = None;
+                                        data.update_simulation();
+                                    })
+                                    .disabled_if(|data: &AppState, _env| {
+                                        data.canvas_image.is_none()
+                                    }),
+                            ),
+                    )
+                    .with_spacer(5.0)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Label::new(|data: &AppState, _env: &Env| {
+                                format!("Correct from screenshot: {}", data.verify_rounds)
+                            }))
+                            .with_child(
+                                Slider::new()
+                                    .with_range(0.0, 3.0)
+                                    .with_step(1.0)
+                                    .lens(AppState::verify_rounds)
+                                    .fix_width(80.0),
+                            )
+                            .with_child(Button::new("Screenshot file").on_click(
+                                |_ctx, data: &mut AppState, _env| {
+                                    data.pick_verify_screenshot();
+                                },
+                            )),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(Either::new(
+                        |data: &AppState, _env| data.checkpoint.is_some(),
+                        Flex::row()
+                            .with_child(draw_button("Resume", true))
+                            .with_spacer(5.0)
+                            .with_child(draw_button("Restart", false)),
+                        draw_button("Draw image", false),
                     ))
                     .with_spacer(10.0)
                     .with_child(Checkbox::new("Dither").lens(AppState::dithering))
                     .with_spacer(10.0)
-                    .with_child(Label::new(|data: &AppState, _env: &Env| {
-                        format!("Pixel interval: {}", data.pixel_interval)
-                    }))
+                    .with_child(
+                        Checkbox::new("Fail-safe")
+                            .lens(AppState::fail_safe.then(FailSafeConfig::enabled)),
+                    )
+                    .with_spacer(3.0)
+                    .with_child(
+                        Button::new(|data: &AppState, _env: &Env| {
+                            format!("Abort corner: {}", data.fail_safe.corner)
+                        })
+                        .on_click(|_ctx, data: &mut AppState, _env| {
+                            data.fail_safe.corner = data.fail_safe.corner.next();
+                        }),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(
+                        Checkbox::new("Humanize motion")
+                            .lens(AppState::humanize.then(HumanizeConfig::enabled)),
+                    )
+                    .with_spacer(3.0)
+                    .with_child(
+                        Flex::row()
+                            .with_child(Label::new("Delay ms"))
+                            .with_child(humanize_field(HumanizeConfig::min_delay))
+                            .with_child(Label::new("-"))
+                            .with_child(humanize_field(HumanizeConfig::max_delay)),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(
+                        Flex::column()
+                            .with_child(Label::new(|data: &AppState, _env: &Env| {
+                                format!("Pixel interval: {}", data.pixel_interval)
+                            }))
+                            .with_spacer(3.0)
+                            .with_child(
+                                Flex::row()
+                                    .with_child(
+                                        Slider::new()
+                                            .with_range(1.0, 5.0)
+                                            .with_step(1.0)
+                                            .lens(AppState::pixel_interval),
+                                    )
+                                    .with_spacer(5.0)
+                                    .with_child(Label::new(|data: &AppState, _env: &Env| {
+                                        data.estimated_time.map_or(String::new(), |time| {
+                                            format!("~{}", format_duration(time))
+                                        })
+                                    })),
+                            )
+                            .with_spacer(3.0)
+                            .with_child(Label::new(|data: &AppState, _env: &Env| {
+                                format!("Brush width: {}", data.brush_width)
+                            }))
+                            .with_spacer(3.0)
+                            .with_child(
+                                Slider::new()
+                                    .with_range(1.0, 10.0)
+                                    .with_step(1.0)
+                                    .lens(AppState::brush_width),
+                            ),
+                    )
+                    .with_spacer(10.0)
+                    .with_child(
+                        ProgressBar::new()
+                            .lens(AppState::progress.map(
+                                |progress| progress.as_ref().map_or(0.0, |p| p.fraction()),
+                                |_, _| (),
+                            ))
+                            .fix_width(200.0),
+                    )
                     .with_spacer(3.0)
                     .with_child(
-                        Slider::new()
-                            .with_range(1.0, 5.0)
-                            .with_step(1.0)
-                            .lens(AppState::pixel_interval),
+                        Label::new(|data: &AppState, _env: &Env| data.status.clone())
+                            .with_line_break_mode(LineBreaking::WordWrap)
+                            .fix_width(280.0),
+                    )
+                    .with_spacer(3.0)
+                    .with_child(
+                        Button::new("Cancel")
+                            .on_click(|_ctx, data: &mut AppState, _env| {
+                                data.hotkeys.trigger(HotkeyAction::Abort);
+                            })
+                            .disabled_if(|data: &AppState, _env| !data.drawing),
                     )
                     .with_flex_child(SizedBox::empty().expand(), 1.0)
                     .background(Color::GRAY)
@@ -75,17 +339,36 @@ pub fn build_root_widget() -> impl Widget<AppState> {
                         ctx.fill(bounds, &Color::rgba8(0, 0, 0, 64));
                         ctx.stroke(Screen::get_display_rect(), &Color::BLUE, 1.0);
                         ctx.stroke(bounds, &Color::GREEN, 1.0);
-                        if let Some(start_pos) = data.start_pos {
+                        let layout = ctx
+                            .text()
+                            .new_text_layout(
+                                "Drag to select the area, drag its inside or edges to adjust it, \
+                                 arrow keys to nudge it. Right click or Enter to finish",
+                            )
+                            .font(FontFamily::SYSTEM_UI, 20.0)
+                            .text_color(Color::WHITE)
+                            .build()
+                            .unwrap();
+                        ctx.draw_text(&layout, (20.0, 20.0));
+                        if let Some(area) = data.area() {
                             ctx.clear(bounds, Color::rgba8(0, 0, 0, 128));
-                            if let Some(end_pos) = data.end_pos {
-                                let rect = druid::Rect::new(
-                                    start_pos.0 - data.x_offset,
-                                    start_pos.1 - data.y_offset,
-                                    end_pos.0 - data.x_offset,
-                                    end_pos.1 - data.y_offset,
-                                );
-                                ctx.clear(rect, Color::TRANSPARENT);
-                                ctx.stroke(rect, &Color::RED, 1.0);
+                            let rect = area - ctx.to_screen(Point::ZERO).to_vec2();
+                            ctx.clear(rect, Color::TRANSPARENT);
+                            ctx.stroke(rect, &Color::RED, 1.0);
+                            let corners = [
+                                (rect.x0, rect.y0),
+                                (rect.x1, rect.y0),
+                                (rect.x0, rect.y1),
+                                (rect.x1, rect.y1),
+                                (rect.center().x, rect.y0),
+                                (rect.center().x, rect.y1),
+                                (rect.x0, rect.center().y),
+                                (rect.x1, rect.center().y),
+                            ];
+                            for handle in corners {
+                                let handle = Rect::from_center_size(handle, (6.0, 6.0));
+                                ctx.fill(handle, &Color::WHITE);
+                                ctx.stroke(handle, &Color::RED, 1.0);
                             }
                         }
                     })
@@ -93,40 +376,412 @@ pub fn build_root_widget() -> impl Widget<AppState> {
                     10.0,
                 ),
             ),
-            ViewStatus::Palette => Box::new(
+            ViewStatus::Palette => Box::new(Flex::column().with_flex_child(
+                palette_markers(Some(
+                    "Backspace undoes the last click, clicking a number moves it. \
+                     Right click to finish",
+                ))
+                .controller(PainterController::new(Mode::Palette)),
+                10.0,
+            )),
+            ViewStatus::PaletteConfirm => Box::new(
                 Flex::column().with_flex_child(
-                    Painter::new(|ctx, data: &AppState, _env| {
-                        let bounds = ctx.size().to_rect();
-
-                        ctx.fill(bounds, &Color::rgba8(0, 0, 0, 16));
-                        for pos in &data.colors_pos {
-                            let text = ctx.text();
-                            let layout = text
-                                .new_text_layout(format!(
-                                    "{}",
-                                    data.colors_pos.iter().position(|&p| p == *pos).unwrap() + 1
-                                ))
-                                // .new_text_layout(format!("{}x{}", pos.0, pos.1))
-                                .font(FontFamily::SERIF, 24.0)
-                                .text_color(Color::RED)
-                                .build()
-                                .unwrap();
-                            let layout_size = layout.size();
-                            ctx.draw_text(
-                                &layout,
-                                (
-                                    pos.0 - layout_size.width / 2.0 - data.x_offset,
-                                    pos.1 - layout_size.height / 2.0 - data.y_offset,
-                                ),
-                            );
-                        }
-                    })
-                    .controller(PainterController::new(Mode::Palette)),
+                    palette_markers(Some(
+                        "Check the detected buttons. Left click to accept, right click to discard",
+                    ))
+                    .controller(PainterController::new(Mode::PaletteConfirm)),
+                    10.0,
+                ),
+            ),
+            ViewStatus::PaletteVerify => Box::new(
+                Flex::column().with_flex_child(
+                    palette_markers(Some(
+                        "Circled buttons show another color. Left click to click just them again, \
+                         right click to close",
+                    ))
+                    .controller(PainterController::new(Mode::PaletteVerify)),
+                    10.0,
+                ),
+            ),
+            ViewStatus::Anchor => Box::new(
+                Flex::column().with_flex_child(
+                    palette_markers(Some(
+                        "Click a point that moves with the game, e.g. a corner of its page. \
+                         Right click to cancel",
+                    ))
+                    .controller(PainterController::new(Mode::Anchor)),
+                    10.0,
+                ),
+            ),
+            ViewStatus::Reanchor => Box::new(
+                Flex::column().with_flex_child(
+                    palette_markers(Some(
+                        "Click where the anchor (blue cross) is now, the calibration moves with it. \
+                         Right click to cancel",
+                    ))
+                    .controller(PainterController::new(Mode::Reanchor)),
                     10.0,
                 ),
             ),
+            ViewStatus::Eraser => Box::new(
+                Flex::column().with_flex_child(
+                    palette_markers(Some(
+                        "Click the eraser button of the game, it clears pixels that should be white. \
+                         Right click to cancel",
+                    ))
+                    .controller(PainterController::new(Mode::Eraser)),
+                    10.0,
+                ),
+            ),
+            ViewStatus::PaletteEditor => Box::new(build_palette_editor()),
+        }
         },
     );
 
     Container::new(view_switcher)
+        .controller(HotkeyController)
+        .controller(DrawingController)
+        .controller(ImageLoadController)
+        .controller(PlanSettingsController)
+}
+
+fn build_palette_editor() -> impl Widget<AppState> {
+    let colors = List::new(|| {
+        Flex::row()
+            .with_child(Label::new(|entry: &PaletteEditorEntry, _env: &Env| {
+                format!("{}.", entry.index + 1)
+            }))
+            .with_spacer(5.0)
+            .with_child(
+                Painter::new(|ctx, entry: &PaletteEditorEntry, _env| {
+                    let bounds = ctx.size().to_rect();
+                    match parse_hex_color(&entry.hex) {
+                        Some(color) => ctx.fill(bounds, &Color::rgb8(color[0], color[1], color[2])),
+                        // Invalid hex values are crossed out
+                        None => ctx.stroke(
+                            druid::kurbo::Line::new(bounds.origin(), (bounds.x1, bounds.y1)),
+                            &Color::RED,
+                            2.0,
+                        ),
+                    }
+                    ctx.stroke(bounds, &Color::BLACK, 1.0);
+                })
+                .fix_size(20.0, 20.0),
+            )
+            .with_spacer(5.0)
+            .with_child(TextBox::new().lens(PaletteEditorEntry::hex).fix_width(80.0))
+            .with_spacer(5.0)
+            .with_child(
+                Label::new(
+                    |entry: &PaletteEditorEntry, _env: &Env| match entry.position {
+                        Some((x, y)) => format!("{:.0}, {:.0}", x, y),
+                        None => String::from("not calibrated"),
+                    },
+                )
+                .fix_width(100.0),
+            )
+            .with_child(palette_edit_button("Up", PaletteEdit::MoveUp))
+            .with_child(palette_edit_button("Down", PaletteEdit::MoveDown))
+            .with_child(palette_edit_button("Remove", PaletteEdit::Remove))
+            .padding(2.0)
+    });
+
+    let screenshot = ViewSwitcher::new(
+        |data: &AppState, _env| data.editor_screenshot.clone(),
+        |screenshot, _data, _env| match screenshot {
+            Some(screenshot) => Box::new(
+                Scroll::new(
+                    Image::new(ImageBuf::from_raw(
+                        screenshot.as_raw().clone(),
+                        ImageFormat::Rgb,
+                        screenshot.width() as usize,
+                        screenshot.height() as usize,
+                    ))
+                    .fill_mode(FillStrat::None)
+                    .controller(ScreenshotSampleController),
+                )
+                .expand(),
+            ),
+            None => Box::new(
+                Label::new("Load a screenshot of the game and click a pixel to add its color")
+                    .with_line_break_mode(LineBreaking::WordWrap)
+                    .center(),
+            ),
+        },
+    );
+
+    Flex::column()
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+        .with_flex_child(
+            Scroll::new(colors.lens(AppState::palette_editor))
+                .vertical()
+                .expand(),
+            1.0,
+        )
+        .with_spacer(5.0)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    Button::new("Add color").on_click(|_ctx, data: &mut AppState, _env| {
+                        data.add_palette_color(Rgb([0, 0, 0]));
+                    }),
+                )
+                .with_spacer(5.0)
+                .with_child(Button::new("Load screenshot").on_click(
+                    |_ctx, data: &mut AppState, _env| {
+                        if let Err(e) = data.load_editor_screenshot() {
+                            show_error("Failed to load screenshot", &e.to_string());
+                        }
+                    },
+                )),
+        )
+        .with_spacer(5.0)
+        .with_flex_child(screenshot, 1.0)
+        .with_spacer(5.0)
+        .with_child(
+            Flex::row()
+                .with_child(
+                    Button::new("Save").on_click(|ctx, data: &mut AppState, _env| {
+                        if let Err(e) = data.save_palette_editor(ctx) {
+                            show_error("Failed to save palette", &e.to_string());
+                        }
+                    }),
+                )
+                .with_spacer(5.0)
+                .with_child(
+                    Button::new("Cancel").on_click(|ctx, data: &mut AppState, _env| {
+                        data.close_palette_editor(ctx);
+                    }),
+                ),
+        )
+        .padding(10.0)
+        .background(Color::GRAY)
+        .controller(PaletteEditorController)
+}
+
+fn palette_edit_button(
+    text: &str,
+    edit: fn(usize) -> PaletteEdit,
+) -> impl Widget<PaletteEditorEntry> {
+    Button::new(text).on_click(
+        move |ctx: &mut EventCtx, entry: &mut PaletteEditorEntry, _env| {
+            ctx.submit_command(PALETTE_EDIT.with(edit(entry.index)));
+        },
+    )
+}
+
+// Numbered markers over the calibrated palette buttons
+fn palette_markers(hint: Option<&'static str>) -> Painter<AppState> {
+    Painter::new(move |ctx, data: &AppState, _env| {
+        let bounds = ctx.size().to_rect();
+        let origin = ctx.to_screen(Point::ZERO);
+
+        ctx.fill(bounds, &Color::rgba8(0, 0, 0, 16));
+        if let Some(hint) = hint {
+            let layout = ctx
+                .text()
+                .new_text_layout(hint)
+                .font(FontFamily::SYSTEM_UI, 20.0)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (20.0, 20.0));
+        }
+        for (index, pos) in data.colors_pos.iter().enumerate() {
+            let Some(pos) = pos else {
+                continue;
+            };
+            let text = ctx.text();
+            let layout = text
+                .new_text_layout(format!("{}", index + 1))
+                // .new_text_layout(format!("{}x{}", pos.0, pos.1))
+                .font(FontFamily::SERIF, 24.0)
+                .text_color(Color::RED)
+                .build()
+                .unwrap();
+            let layout_size = layout.size();
+            ctx.draw_text(
+                &layout,
+                (
+                    pos.0 - layout_size.width / 2.0 - origin.x,
+                    pos.1 - layout_size.height / 2.0 - origin.y,
+                ),
+            );
+            let center = Point::new(pos.0 - origin.x, pos.1 - origin.y);
+            if data.palette_mismatches.contains(&index) {
+                ctx.stroke(Circle::new(center, 18.0), &Color::RED, 3.0);
+            }
+            // Picked up, the next click moves it
+            if data.view_status == ViewStatus::Palette && data.palette_target == Some(index) {
+                ctx.stroke(Circle::new(center, 18.0), &Color::WHITE, 2.0);
+            }
+        }
+        // Which button to click next, with the color it should have
+        let next = data.next_palette_slot();
+        if let (ViewStatus::Palette, Some((next, color))) = (
+            data.view_status,
+            next.and_then(|next| Some((next, *data.palette.get(next)?))),
+        ) {
+            let layout = ctx
+                .text()
+                .new_text_layout(format!("Next: {}", next + 1))
+                .font(FontFamily::SYSTEM_UI, 20.0)
+                .text_color(Color::WHITE)
+                .build()
+                .unwrap();
+            ctx.draw_text(&layout, (20.0, 50.0));
+            let swatch = Rect::from_origin_size(
+                (30.0 + layout.size().width, 50.0),
+                (layout.size().height, layout.size().height),
+            );
+            ctx.fill(swatch, &Color::rgb8(color[0], color[1], color[2]));
+            ctx.stroke(swatch, &Color::WHITE, 1.0);
+        }
+        if let Some(eraser) = data.profile.tool(ERASER_TOOL) {
+            let layout = ctx
+                .text()
+                .new_text_layout("E")
+                .font(FontFamily::SERIF, 24.0)
+                .text_color(Color::RED)
+                .build()
+                .unwrap();
+            let layout_size = layout.size();
+            ctx.draw_text(
+                &layout,
+                (
+                    eraser.0 - layout_size.width / 2.0 - origin.x,
+                    eraser.1 - layout_size.height / 2.0 - origin.y,
+                ),
+            );
+        }
+        // The point the calibration is relative to
+        if let Some(anchor) = data.profile.anchor {
+            let anchor = Point::new(anchor.0 - origin.x, anchor.1 - origin.y);
+            for arm in [Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0)] {
+                ctx.stroke(
+                    druid::kurbo::Line::new(anchor - arm, anchor + arm),
+                    &Color::AQUA,
+                    2.0,
+                );
+            }
+        }
+    })
+}
+
+// x, y, width and height of the drawing area, applied on Enter or when leaving the field
+fn build_area_panel() -> impl Widget<AppState> {
+    Flex::column()
+        .with_child(
+            Flex::row()
+                .with_child(area_field(
+                    "x",
+                    |area| area.x0,
+                    |area, x| area.with_origin((x, area.y0)),
+                ))
+                .with_child(area_field(
+                    "y",
+                    |area| area.y0,
+                    |area, y| area.with_origin((area.x0, y)),
+                ))
+                .with_child(area_field(
+                    "w",
+                    |area| area.width(),
+                    |area, width| area.with_size((width, area.height())),
+                ))
+                .with_child(area_field(
+                    "h",
+                    |area| area.height(),
+                    |area, height| area.with_size((area.width(), height)),
+                )),
+        )
+        .with_child(Checkbox::new("Lock aspect to image").lens(AppState::lock_aspect))
+        .controller(AreaPanelController)
+}
+
+fn area_field(
+    label: &'static str,
+    get: fn(&Rect) -> f64,
+    set: fn(&Rect, f64) -> Rect,
+) -> impl Widget<AppState> {
+    let lens = druid::lens::Map::new(
+        move |data: &AppState| data.area().map_or(0.0, |area| get(&area).round()),
+        move |data: &mut AppState, value: f64| {
+            let area = data.area().unwrap_or_default();
+            if get(&area).round() == value {
+                return;
+            }
+            let mut edited = set(&area, value);
+            if let Some(aspect) = data.area_aspect() {
+                // Width and height follow each other, the position stays
+                edited = if edited.height() != area.height() {
+                    edited.with_size((edited.height() * aspect, edited.height()))
+                } else {
+                    edited.with_size((edited.width(), edited.width() / aspect))
+                };
+            }
+            data.set_area(edited);
+        },
+    );
+    Flex::row().with_child(Label::new(label)).with_child(
+        TextBox::new()
+            .with_formatter(ParseFormatter::new())
+            .update_data_while_editing(false)
+            .fix_width(50.0)
+            .lens(lens),
+    )
+}
+
+fn humanize_field(field: impl Lens<HumanizeConfig, f64> + 'static) -> impl Widget<AppState> {
+    TextBox::new()
+        .with_formatter(ParseFormatter::new())
+        .update_data_while_editing(false)
+        .fix_width(40.0)
+        .lens(AppState::humanize.then(field))
+        .disabled_if(|data: &AppState, _env| !data.humanize.enabled)
+}
+
+pub fn build_preview() -> impl Widget<AppState> {
+    Flex::column()
+        .with_flex_child(
+            Tabs::new()
+                .with_tab(
+                    "Original",
+                    ZoomImage::new(|data: &AppState| &data.source_image),
+                )
+                .with_tab(
+                    "Quantized",
+                    ZoomImage::new(|data: &AppState| &data.current_image),
+                )
+                .with_tab(
+                    "Simulated",
+                    ZoomImage::new(|data: &AppState| &data.simulated_image),
+                ),
+            1.0,
+        )
+        .with_child(
+            Flex::row()
+                .with_flex_child(
+                    Label::new(|data: &AppState, _env: &Env| data.preview_stats.clone())
+                        .expand_width(),
+                    1.0,
+                )
+                .with_child(Button::new("Save simulation").on_click(
+                    |_ctx, data: &mut AppState, _env| {
+                        if let Err(e) = data.save_simulation() {
+                            show_error("Failed to save simulation", &e.to_string());
+                        }
+                    },
+                ))
+                .padding(5.0),
+        )
+}
+
+fn draw_button(text: &str, resume: bool) -> impl Widget<AppState> {
+    Button::new(text)
+        .on_click(move |ctx, data: &mut AppState, _env| {
+            if let Err(e) = data.draw_image(ctx, resume) {
+                show_error("Cannot start drawing", &e.to_string());
+            }
+        })
+        .disabled_if(|data: &AppState, _env| data.drawing)
 }
@@ -0,0 +1,114 @@
+use druid::image::{ImageBuffer, Rgb};
+use druid::piet::{ImageFormat, InterpolationMode, PietImage};
+use druid::{
+    Affine, BoxConstraints, Color, Data, Env, Event, EventCtx, LayoutCtx, LifeCycle, LifeCycleCtx,
+    PaintCtx, Point, Rect, RenderContext, Size, UpdateCtx, Vec2, Widget,
+};
+
+const MIN_ZOOM: f64 = 0.1;
+const MAX_ZOOM: f64 = 32.0;
+
+// Shows an image that can be zoomed with the mouse wheel and panned by dragging.
+// Double click resets the view
+pub struct ZoomImage<T> {
+    image: fn(&T) -> &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    paint_image: Option<PietImage>,
+    zoom: f64,
+    offset: Vec2,
+    drag_from: Option<Point>,
+}
+
+impl<T> ZoomImage<T> {
+    pub fn new(image: fn(&T) -> &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Self {
+        ZoomImage {
+            image,
+            paint_image: None,
+            zoom: 1.0,
+            offset: Vec2::ZERO,
+            drag_from: None,
+        }
+    }
+}
+
+impl<T: Data> Widget<T> for ZoomImage<T> {
+    fn event(&mut self, ctx: &mut EventCtx, event: &Event, _data: &mut T, _env: &Env) {
+        match event {
+            Event::Wheel(me) => {
+                let zoom =
+                    (self.zoom * 1.1f64.powf(-me.wheel_delta.y / 100.0)).clamp(MIN_ZOOM, MAX_ZOOM);
+                // Keep the pixel under the pointer in place
+                let image_pos = (me.pos.to_vec2() - self.offset) / self.zoom;
+                self.offset = me.pos.to_vec2() - image_pos * zoom;
+                self.zoom = zoom;
+                ctx.request_paint();
+                ctx.set_handled();
+            }
+            Event::MouseDown(me) if me.count == 2 => {
+                self.zoom = 1.0;
+                self.offset = Vec2::ZERO;
+                ctx.request_paint();
+            }
+            Event::MouseDown(me) => {
+                ctx.set_active(true);
+                self.drag_from = Some(me.pos);
+            }
+            Event::MouseMove(me) if ctx.is_active() => {
+                if let Some(from) = self.drag_from.replace(me.pos) {
+                    self.offset += me.pos - from;
+                    ctx.request_paint();
+                }
+            }
+            Event::MouseUp(_) if ctx.is_active() => {
+                ctx.set_active(false);
+                self.drag_from = None;
+            }
+            _ => (),
+        }
+    }
+
+    fn lifecycle(&mut self, _ctx: &mut LifeCycleCtx, _event: &LifeCycle, _data: &T, _env: &Env) {}
+
+    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &T, data: &T, _env: &Env) {
+        if (self.image)(old_data) != (self.image)(data) {
+            self.paint_image = None;
+            ctx.request_layout();
+        }
+    }
+
+    fn layout(&mut self, _ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &T, _env: &Env) -> Size {
+        let (width, height) = (self.image)(data).dimensions();
+        if bc.is_width_bounded() && bc.is_height_bounded() {
+            bc.max()
+        } else {
+            bc.constrain((width as f64, height as f64))
+        }
+    }
+
+    fn paint(&mut self, ctx: &mut PaintCtx, data: &T, _env: &Env) {
+        let bounds = ctx.size().to_rect();
+        ctx.fill(bounds, &Color::grey8(64));
+
+        let image = (self.image)(data);
+        if self.paint_image.is_none() {
+            self.paint_image = ctx
+                .make_image(
+                    image.width() as usize,
+                    image.height() as usize,
+                    image.as_raw(),
+                    ImageFormat::Rgb,
+                )
+                .ok();
+        }
+        let Some(paint_image) = &self.paint_image else {
+            return;
+        };
+
+        let image_rect = Rect::new(0.0, 0.0, image.width() as f64, image.height() as f64);
+        let transform = Affine::translate(self.offset) * Affine::scale(self.zoom);
+        ctx.with_save(|ctx| {
+            ctx.clip(bounds);
+            ctx.transform(transform);
+            ctx.draw_image(paint_image, image_rect, InterpolationMode::NearestNeighbor);
+        });
+    }
+}
@@ -0,0 +1,91 @@
+use druid::image::{ImageBuffer, Rgb};
+use druid::Rect;
+
+// Canvases are white, but screenshots may be slightly tinted
+const MIN_CHANNEL: u8 = 240;
+// Smaller white areas are buttons, text fields and such
+const MIN_CANVAS_SIZE: f64 = 64.0;
+
+// Finds the largest near-white rectangle of a screenshot, in screenshot pixels
+pub fn detect_canvas(screenshot: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Option<Rect> {
+    let (width, height) = screenshot.dimensions();
+    // Number of near-white pixels above and including the current row, per column
+    let mut heights = vec![0u32; width as usize];
+    let mut best: Option<(u64, Rect)> = None;
+
+    for y in 0..height {
+        for x in 0..width {
+            let pixel = screenshot.get_pixel(x, y);
+            let white = pixel.0.iter().all(|&channel| channel >= MIN_CHANNEL);
+            heights[x as usize] = if white { heights[x as usize] + 1 } else { 0 };
+        }
+
+        // Largest rectangle in the histogram of this row, with a stack of
+        // columns whose heights are increasing
+        let mut stack: Vec<usize> = Vec::new();
+        for x in 0..=width as usize {
+            let current = heights.get(x).copied().unwrap_or(0);
+            while let Some(&top) = stack.last() {
+                if heights[top] < current {
+                    break;
+                }
+                stack.pop();
+                let left = stack.last().map_or(0, |&left| left + 1);
+                let rect_height = heights[top];
+                let area = (x - left) as u64 * rect_height as u64;
+                if best.is_none_or(|(best_area, _)| area > best_area) {
+                    let rect = Rect::new(
+                        left as f64,
+                        (y + 1 - rect_height) as f64,
+                        x as f64,
+                        (y + 1) as f64,
+                    );
+                    best = Some((area, rect));
+                }
+            }
+            stack.push(x);
+        }
+    }
+
+    best.map(|(_, rect)| rect)
+        .filter(|rect| rect.width() >= MIN_CANVAS_SIZE && rect.height() >= MIN_CANVAS_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screenshot_with_canvas(canvas: Rect) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(400, 300, |x, y| {
+            if canvas.contains((x as f64 + 0.5, y as f64 + 0.5).into()) {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([128, 128, 128])
+            }
+        })
+    }
+
+    #[test]
+    fn finds_white_rectangle_on_gray() {
+        let canvas = Rect::new(50.0, 40.0, 350.0, 240.0);
+        assert_eq!(detect_canvas(&screenshot_with_canvas(canvas)), Some(canvas));
+    }
+
+    #[test]
+    fn slightly_tinted_white_counts() {
+        let mut screenshot = screenshot_with_canvas(Rect::new(10.0, 10.0, 110.0, 90.0));
+        for pixel in screenshot.pixels_mut().filter(|pixel| pixel.0[0] == 255) {
+            *pixel = Rgb([245, 248, 242]);
+        }
+        assert_eq!(
+            detect_canvas(&screenshot),
+            Some(Rect::new(10.0, 10.0, 110.0, 90.0))
+        );
+    }
+
+    #[test]
+    fn small_white_areas_are_ignored() {
+        let screenshot = screenshot_with_canvas(Rect::new(10.0, 10.0, 50.0, 40.0));
+        assert_eq!(detect_canvas(&screenshot), None);
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::BotError;
+
+use super::draw_plan::DrawPlan;
+
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+// How far drawing got. `stroke_index` strokes of the color at `color_index`
+// and all colors before it are done
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub plan_hash: u64,
+    pub color_index: usize,
+    pub stroke_index: usize,
+}
+
+impl Checkpoint {
+    pub fn start(plan: &DrawPlan) -> Self {
+        Checkpoint {
+            plan_hash: plan_hash(plan),
+            color_index: 0,
+            stroke_index: 0,
+        }
+    }
+
+    // Strokes of `plan` that are already drawn
+    pub fn strokes_done(&self, plan: &DrawPlan) -> usize {
+        let previous: usize = plan
+            .colors
+            .iter()
+            .take(self.color_index)
+            .map(|strokes| strokes.lines.len())
+            .sum();
+        previous + self.stroke_index
+    }
+
+    // False if the image or the settings changed since the checkpoint was taken
+    pub fn matches(&self, plan: &DrawPlan) -> bool {
+        self.plan_hash == plan_hash(plan)
+    }
+
+    pub fn save(&self) -> Result<(), BotError> {
+        self.save_to(Path::new(CHECKPOINT_FILE))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), BotError> {
+        let json = serde_json::to_string(self).map_err(BotError::Checkpoint)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+// FNV-1a, unlike DefaultHasher it gives the same hash in every build, so a
+// checkpoint stays valid across updates
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// Identifies a plan, so a checkpoint is never resumed with another image or settings
+pub fn plan_hash(plan: &DrawPlan) -> u64 {
+    let mut hash = FNV_OFFSET;
+    let mut write = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash = (hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    };
+    for strokes in &plan.colors {
+        write(&strokes.color.0);
+        for ((x0, y0), (x1, y1)) in &strokes.lines {
+            for value in [x0, y0, x1, y1] {
+                write(&value.to_bits().to_le_bytes());
+            }
+        }
+    }
+    hash
+}
+
+pub fn load_checkpoint() -> Option<Checkpoint> {
+    load_checkpoint_from(Path::new(CHECKPOINT_FILE))
+}
+
+fn load_checkpoint_from(path: &Path) -> Option<Checkpoint> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+pub fn clear_checkpoint() -> Result<(), BotError> {
+    match fs::remove_file(CHECKPOINT_FILE) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::image::{ImageBuffer, Rgb};
+
+    use super::*;
+    use crate::utils::draw_plan::ColorStrokes;
+
+    fn plan(lines: Vec<((f64, f64), (f64, f64))>) -> DrawPlan {
+        DrawPlan {
+            colors: vec![
+                ColorStrokes {
+                    color: Rgb([255, 0, 0]),
+                    lines: lines.clone(),
+                },
+                ColorStrokes {
+                    color: Rgb([0, 0, 255]),
+                    lines,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}.json", std::process::id()));
+        let checkpoint = Checkpoint {
+            color_index: 1,
+            stroke_index: 1,
+            ..Checkpoint::start(&plan(vec![((0.0, 0.0), (0.0, 5.0))]))
+        };
+
+        checkpoint.save_to(&path).unwrap();
+        let loaded = load_checkpoint_from(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded, Some(checkpoint));
+    }
+
+    #[test]
+    fn missing_file_loads_nothing() {
+        let path = std::env::temp_dir().join("no-such-checkpoint.json");
+        assert_eq!(load_checkpoint_from(&path), None);
+    }
+
+    #[test]
+    fn another_plan_does_not_match() {
+        let original = plan(vec![((0.0, 0.0), (0.0, 5.0)), ((2.0, 0.0), (2.0, 5.0))]);
+        let changed = plan(vec![((0.0, 0.0), (0.0, 5.0)), ((2.0, 0.0), (2.0, 6.0))]);
+        let checkpoint = Checkpoint::start(&original);
+
+        assert!(checkpoint.matches(&original));
+        assert!(!checkpoint.matches(&changed));
+    }
+
+    #[test]
+    fn same_image_gives_the_same_plan_and_hash() {
+        // Colors with as many strokes as each other, their order comes from the tiebreak
+        let colors = [
+            Rgb([255, 0, 0]),
+            Rgb([0, 255, 0]),
+            Rgb([0, 0, 255]),
+            Rgb([0, 0, 0]),
+        ];
+        let image = ImageBuffer::from_fn(8, 4, |x, _| colors[x as usize / 2]);
+
+        let first = DrawPlan::from_image(&image, 1);
+        let second = DrawPlan::from_image(&image, 1);
+        assert_eq!(first, second);
+        assert_eq!(plan_hash(&first), plan_hash(&second));
+        let order: Vec<Rgb<u8>> = first.colors.iter().map(|strokes| strokes.color).collect();
+        assert_eq!(order, [colors[3], colors[2], colors[1], colors[0]]);
+    }
+
+    #[test]
+    fn hash_is_stable() {
+        // Saved checkpoints must keep matching after an update
+        assert_eq!(plan_hash(&DrawPlan::default()), FNV_OFFSET);
+        let plan = plan(vec![((0.0, 0.0), (0.0, 5.0))]);
+        assert_eq!(plan_hash(&plan), 0x6174_20a9_27c2_368b);
+    }
+
+    #[test]
+    fn strokes_done_counts_previous_colors() {
+        let plan = plan(vec![((0.0, 0.0), (0.0, 5.0)), ((2.0, 0.0), (2.0, 5.0))]);
+        let checkpoint = Checkpoint {
+            color_index: 1,
+            stroke_index: 1,
+            ..Checkpoint::start(&plan)
+        };
+        assert_eq!(checkpoint.strokes_done(&plan), 3);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use druid::image::{ImageBuffer, Rgb};
+use serde::{Serialize, Serializer};
+
+use super::image_utils::{color_distance, color_hex};
+
+pub const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+
+// Line coordinates are relative to the top left corner of the drawing area
+pub type Line = ((f64, f64), (f64, f64));
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ColorStrokes {
+    #[serde(serialize_with = "serialize_hex")]
+    pub color: Rgb<u8>,
+    pub lines: Vec<Line>,
+}
+
+// Strokes grouped by color, in the order they are drawn
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DrawPlan {
+    pub colors: Vec<ColorStrokes>,
+}
+
+// Colors are written as "#RRGGBB", like in the palette editor
+fn serialize_hex<S: Serializer>(color: &Rgb<u8>, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&color_hex(color))
+}
+
+// Canvas pixels closer than this to the target color don't have to be redrawn
+const DIFF_TOLERANCE: f64 = 24.0;
+
+impl DrawPlan {
+    pub fn from_image(image: &ImageBuffer<Rgb<u8>, Vec<u8>>, pixels_interval: u8) -> Self {
+        // The canvas is already white, so background lines are never drawn
+        Self::from_pixels(image.dimensions(), pixels_interval, |x, y| {
+            Some(*image.get_pixel(x, y)).filter(|color| *color != BACKGROUND)
+        })
+    }
+
+    // Only draws the pixels of `image` that differ on `canvas`, an image of the
+    // current canvas of the same size. Pixels that have to be cleared are drawn
+    // in the background color, after everything else
+    pub fn from_diff(
+        image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        canvas: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        pixels_interval: u8,
+    ) -> Self {
+        Self::from_pixels(image.dimensions(), pixels_interval, |x, y| {
+            let color = *image.get_pixel(x, y);
+            let current = canvas.get_pixel_checked(x, y).unwrap_or(&BACKGROUND);
+            (color_distance(&color, current) > DIFF_TOLERANCE).then_some(color)
+        })
+    }
+
+    // Pixels of `image` that `canvas` is missing, without clearing anything.
+    // For touching up a drawing, where wider strokes of the background color
+    // would also paint over correct pixels next to the wrong ones
+    pub fn missing(
+        image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        canvas: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    ) -> Self {
+        Self::from_pixels(image.dimensions(), 1, |x, y| {
+            missing_color(image, canvas, x, y)
+        })
+    }
+
+    // `pixel` gives the color to draw at a pixel, None where nothing is drawn
+    fn from_pixels(
+        (width, height): (u32, u32),
+        pixels_interval: u8,
+        pixel: impl Fn(u32, u32) -> Option<Rgb<u8>>,
+    ) -> Self {
+        let pixels_interval = pixels_interval.max(1);
+        let (draw_vertically_lines, nb_vertical_lines) =
+            extract_lines_to_draw((width, height), true, pixels_interval, &pixel);
+        let (draw_horizontally_lines, nb_horizontal_lines) =
+            extract_lines_to_draw((width, height), false, pixels_interval, &pixel);
+
+        let lines = if nb_vertical_lines > nb_horizontal_lines {
+            draw_horizontally_lines
+        } else {
+            draw_vertically_lines
+        };
+
+        let mut colors: Vec<ColorStrokes> = lines
+            .into_iter()
+            .map(|(color, lines)| ColorStrokes { color, lines })
+            .collect();
+        // The colors come out of a HashMap, ties are broken by the color so
+        // the same image always gives the same plan
+        colors.sort_by_key(|strokes| {
+            (
+                strokes.color == BACKGROUND,
+                std::cmp::Reverse(strokes.lines.len()),
+                strokes.color.0,
+            )
+        });
+
+        DrawPlan { colors }
+    }
+
+    pub fn stroke_count(&self) -> usize {
+        self.colors.iter().map(|strokes| strokes.lines.len()).sum()
+    }
+}
+
+fn missing_color(
+    image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    canvas: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    x: u32,
+    y: u32,
+) -> Option<Rgb<u8>> {
+    let color = *image.get_pixel(x, y);
+    let current = canvas.get_pixel_checked(x, y).unwrap_or(&BACKGROUND);
+    (color != BACKGROUND && color_distance(&color, current) > DIFF_TOLERANCE).then_some(color)
+}
+
+// Number of pixels DrawPlan::missing would draw
+pub fn count_missing(
+    image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    canvas: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+) -> usize {
+    let (width, height) = image.dimensions();
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| missing_color(image, canvas, x, y).is_some())
+        .count()
+}
+
+fn extract_lines_to_draw(
+    (width, height): (u32, u32),
+    vertically: bool,
+    pixels_interval: u8,
+    pixel: &impl Fn(u32, u32) -> Option<Rgb<u8>>,
+) -> (HashMap<Rgb<u8>, Vec<Line>>, i32) {
+    let (w, h) = if vertically {
+        (width, height)
+    } else {
+        (height, width)
+    };
+
+    let mut lines: HashMap<Rgb<u8>, Vec<Line>> = HashMap::new();
+    let mut nb_lines = 0;
+
+    for x in (0..w).step_by(pixels_interval as usize) {
+        // The line being extended and its color
+        let mut line: Option<(Rgb<u8>, Line)> = None;
+
+        for y in (0..h).step_by(pixels_interval as usize) {
+            let (color, current_position) = if vertically {
+                (pixel(x, y), (x as f64, y as f64))
+            } else {
+                (pixel(y, x), (y as f64, x as f64))
+            };
+
+            match (&mut line, color) {
+                (Some((line_color, (_, line_end))), Some(color)) if *line_color == color => {
+                    *line_end = current_position;
+                }
+                _ => {
+                    if let Some((line_color, finished)) = line.take() {
+                        nb_lines += 1;
+                        lines.entry(line_color).or_default().push(finished);
+                    }
+                    line = color.map(|color| (color, (current_position, current_position)));
+                }
+            }
+        }
+
+        if let Some((line_color, finished)) = line {
+            nb_lines += 1;
+            lines.entry(line_color).or_default().push(finished);
+        }
+    }
+
+    (lines, nb_lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+    const BLUE: Rgb<u8> = Rgb([0, 0, 255]);
+
+    fn stripes() -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(8, 6, |x, _| if x < 4 { RED } else { BLUE })
+    }
+
+    #[test]
+    fn identical_images_need_no_strokes() {
+        let image = stripes();
+        assert_eq!(DrawPlan::from_diff(&image, &image, 1), DrawPlan::default());
+    }
+
+    #[test]
+    fn single_changed_pixel_is_one_stroke() {
+        let image = stripes();
+        let mut canvas = image.clone();
+        canvas.put_pixel(5, 2, RED);
+
+        let plan = DrawPlan::from_diff(&image, &canvas, 1);
+        assert_eq!(
+            plan.colors,
+            vec![ColorStrokes {
+                color: BLUE,
+                lines: vec![((5.0, 2.0), (5.0, 2.0))],
+            }]
+        );
+    }
+
+    #[test]
+    fn pixels_to_clear_come_last() {
+        let image = ImageBuffer::from_fn(4, 4, |x, _| if x == 0 { RED } else { BACKGROUND });
+        let canvas = ImageBuffer::from_pixel(4, 4, BLUE);
+
+        let plan = DrawPlan::from_diff(&image, &canvas, 1);
+        let colors: Vec<Rgb<u8>> = plan.colors.iter().map(|strokes| strokes.color).collect();
+        assert_eq!(colors, vec![RED, BACKGROUND]);
+    }
+
+    #[test]
+    fn close_colors_are_not_redrawn() {
+        let image = stripes();
+        let canvas = ImageBuffer::from_fn(8, 6, |x, y| {
+            let Rgb([r, g, b]) = *image.get_pixel(x, y);
+            Rgb([r.saturating_sub(5), g + 5, b.saturating_sub(5)])
+        });
+        assert_eq!(DrawPlan::from_diff(&image, &canvas, 1).stroke_count(), 0);
+    }
+
+    #[test]
+    fn missing_pixels_of_a_capture() {
+        let expected = stripes();
+        // Half of the red stripe was drawn, and a blue pixel landed outside
+        let capture = ImageBuffer::from_fn(8, 6, |x, y| match (x, y) {
+            (0..=1, _) => RED,
+            (4..=7, _) => BLUE,
+            _ => BACKGROUND,
+        });
+        let mut capture_with_stray = capture.clone();
+        capture_with_stray.put_pixel(2, 0, BLUE);
+
+        assert_eq!(count_missing(&expected, &capture), 12);
+        assert_eq!(count_missing(&expected, &capture_with_stray), 12);
+        let plan = DrawPlan::missing(&expected, &capture_with_stray);
+        assert_eq!(
+            plan.colors,
+            vec![ColorStrokes {
+                color: RED,
+                lines: vec![((2.0, 0.0), (2.0, 5.0)), ((3.0, 0.0), (3.0, 5.0))],
+            }]
+        );
+        assert_eq!(count_missing(&expected, &expected), 0);
+    }
+
+    #[test]
+    fn missing_never_clears() {
+        let expected = ImageBuffer::from_pixel(4, 4, BACKGROUND);
+        let capture = ImageBuffer::from_pixel(4, 4, BLUE);
+        assert_eq!(DrawPlan::missing(&expected, &capture), DrawPlan::default());
+        assert_eq!(count_missing(&expected, &capture), 0);
+    }
+
+    #[test]
+    fn from_image_skips_the_background() {
+        let image = ImageBuffer::from_fn(4, 4, |x, _| if x == 1 { RED } else { BACKGROUND });
+        let plan = DrawPlan::from_image(&image, 1);
+        assert_eq!(
+            plan.colors,
+            vec![ColorStrokes {
+                color: RED,
+                lines: vec![((1.0, 0.0), (1.0, 3.0))],
+            }]
+        );
+    }
+}
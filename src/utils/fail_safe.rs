@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::mpsc::Receiver;
+
+use druid::{Data, Lens, Rect};
+
+#[derive(Copy, Clone, Debug, Data, PartialEq, Eq)]
+pub enum ScreenCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ScreenCorner {
+    pub fn next(self) -> Self {
+        match self {
+            ScreenCorner::TopLeft => ScreenCorner::TopRight,
+            ScreenCorner::TopRight => ScreenCorner::BottomRight,
+            ScreenCorner::BottomRight => ScreenCorner::BottomLeft,
+            ScreenCorner::BottomLeft => ScreenCorner::TopLeft,
+        }
+    }
+
+    fn contains(self, screen: Rect, size: f64, pos: (f64, f64)) -> bool {
+        let near_left = pos.0 <= screen.x0 + size;
+        let near_right = pos.0 >= screen.x1 - size;
+        let near_top = pos.1 <= screen.y0 + size;
+        let near_bottom = pos.1 >= screen.y1 - size;
+        match self {
+            ScreenCorner::TopLeft => near_left && near_top,
+            ScreenCorner::TopRight => near_right && near_top,
+            ScreenCorner::BottomLeft => near_left && near_bottom,
+            ScreenCorner::BottomRight => near_right && near_bottom,
+        }
+    }
+}
+
+impl fmt::Display for ScreenCorner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScreenCorner::TopLeft => "top left",
+            ScreenCorner::TopRight => "top right",
+            ScreenCorner::BottomLeft => "bottom left",
+            ScreenCorner::BottomRight => "bottom right",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Clone, Data, Lens)]
+pub struct FailSafeConfig {
+    pub enabled: bool,
+    // How far the real pointer may be from what we synthesized, in pixels
+    pub tolerance: f64,
+    pub corner: ScreenCorner,
+    pub corner_size: f64,
+}
+
+impl Default for FailSafeConfig {
+    fn default() -> Self {
+        FailSafeConfig {
+            enabled: true,
+            tolerance: 20.0,
+            corner: ScreenCorner::TopLeft,
+            corner_size: 5.0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FailSafeTrip {
+    PointerMoved {
+        expected: (f64, f64),
+        actual: (f64, f64),
+    },
+    Corner(ScreenCorner),
+}
+
+impl fmt::Display for FailSafeTrip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailSafeTrip::PointerMoved { expected, actual } => write!(
+                f,
+                "pointer moved to {:.0}x{:.0}, expected {:.0}x{:.0}",
+                actual.0, actual.1, expected.0, expected.1
+            ),
+            FailSafeTrip::Corner(corner) => {
+                write!(f, "pointer pushed into the {} screen corner", corner)
+            }
+        }
+    }
+}
+
+// Pointer reports from the listener lag behind what we simulate, so every
+// report is compared with the last few synthesized positions, not only the latest
+const SYNTHESIZED_HISTORY: usize = 64;
+
+pub struct FailSafe {
+    config: FailSafeConfig,
+    screen: Rect,
+    pointer: Receiver<(f64, f64)>,
+    synthesized: VecDeque<(f64, f64)>,
+}
+
+impl FailSafe {
+    pub fn new(config: FailSafeConfig, screen: Rect, pointer: Receiver<(f64, f64)>) -> Self {
+        FailSafe {
+            config,
+            screen,
+            pointer,
+            synthesized: VecDeque::with_capacity(SYNTHESIZED_HISTORY),
+        }
+    }
+
+    pub fn record(&mut self, pos: (f64, f64)) {
+        if self.synthesized.len() == SYNTHESIZED_HISTORY {
+            self.synthesized.pop_front();
+        }
+        self.synthesized.push_back(pos);
+    }
+
+    // Forgets everything reported so far, e.g. after the user had the mouse while paused
+    pub fn reset(&mut self) {
+        while self.pointer.try_recv().is_ok() {}
+        self.synthesized.clear();
+    }
+
+    pub fn check(&mut self) -> Result<(), FailSafeTrip> {
+        let reports: Vec<(f64, f64)> = self.pointer.try_iter().collect();
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        for actual in reports {
+            if self
+                .config
+                .corner
+                .contains(self.screen, self.config.corner_size, actual)
+            {
+                return Err(FailSafeTrip::Corner(self.config.corner));
+            }
+
+            let Some(&expected) = self.synthesized.back() else {
+                continue;
+            };
+            let matches_synthesized = self
+                .synthesized
+                .iter()
+                .any(|pos| distance(*pos, actual) <= self.config.tolerance);
+            if !matches_synthesized {
+                return Err(FailSafeTrip::PointerMoved { expected, actual });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{self, Sender};
+
+    use super::*;
+
+    fn fail_safe() -> (FailSafe, Sender<(f64, f64)>) {
+        let (tx, rx) = mpsc::channel();
+        let screen = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        (FailSafe::new(FailSafeConfig::default(), screen, rx), tx)
+    }
+
+    #[test]
+    fn synthesized_positions_pass() {
+        let (mut fail_safe, pointer) = fail_safe();
+        fail_safe.record((100.0, 100.0));
+        fail_safe.record((500.0, 300.0));
+        fail_safe.record((900.0, 600.0));
+        // Reports lag behind, an older position is fine too
+        pointer.send((100.0, 100.0)).unwrap();
+        pointer.send((505.0, 295.0)).unwrap();
+        assert_eq!(fail_safe.check(), Ok(()));
+    }
+
+    #[test]
+    fn outside_movement_trips() {
+        let (mut fail_safe, pointer) = fail_safe();
+        fail_safe.record((100.0, 100.0));
+        pointer.send((400.0, 100.0)).unwrap();
+        assert_eq!(
+            fail_safe.check(),
+            Err(FailSafeTrip::PointerMoved {
+                expected: (100.0, 100.0),
+                actual: (400.0, 100.0),
+            })
+        );
+    }
+
+    #[test]
+    fn corner_trips_immediately() {
+        let (mut fail_safe, pointer) = fail_safe();
+        // Even a position the bot moved to itself
+        fail_safe.record((2.0, 2.0));
+        pointer.send((2.0, 2.0)).unwrap();
+        assert_eq!(
+            fail_safe.check(),
+            Err(FailSafeTrip::Corner(ScreenCorner::TopLeft))
+        );
+    }
+
+    #[test]
+    fn disabled_never_trips() {
+        let (tx, rx) = mpsc::channel();
+        let config = FailSafeConfig {
+            enabled: false,
+            ..FailSafeConfig::default()
+        };
+        let mut fail_safe = FailSafe::new(config, Rect::new(0.0, 0.0, 100.0, 100.0), rx);
+        fail_safe.record((50.0, 50.0));
+        tx.send((0.0, 0.0)).unwrap();
+        tx.send((90.0, 10.0)).unwrap();
+        assert_eq!(fail_safe.check(), Ok(()));
+    }
+
+    #[test]
+    fn reset_forgets_reports() {
+        let (mut fail_safe, pointer) = fail_safe();
+        fail_safe.record((100.0, 100.0));
+        pointer.send((800.0, 800.0)).unwrap();
+        fail_safe.reset();
+        assert_eq!(fail_safe.check(), Ok(()));
+    }
+}
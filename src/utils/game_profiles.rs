@@ -0,0 +1,61 @@
+use druid::image::Rgb;
+
+use super::profile::{PaletteLayout, Profile};
+
+pub const GARTIC_PHONE: &str = "Gartic Phone";
+pub const SKRIBBL_IO: &str = "skribbl.io";
+pub const DRAWIZE: &str = "Drawize";
+pub const PAINT: &str = "Paint";
+
+pub const BUILTIN_PROFILE_NAMES: [&str; 4] = [GARTIC_PHONE, SKRIBBL_IO, DRAWIZE, PAINT];
+
+pub fn builtin_profile(name: &str) -> Option<Profile> {
+    let (colors, columns): (&[u32], u32) = match name {
+        GARTIC_PHONE => (&GARTIC_PHONE_COLORS, 3),
+        SKRIBBL_IO => (&SKRIBBL_IO_COLORS, 13),
+        DRAWIZE => (&DRAWIZE_COLORS, 8),
+        PAINT => (&PAINT_COLORS, 10),
+        _ => return None,
+    };
+
+    let palette: Vec<Rgb<u8>> = colors.iter().map(|&hex| rgb(hex)).collect();
+    let layout = PaletteLayout {
+        columns,
+        rows: palette.len() as u32 / columns,
+    };
+    Some(Profile::new(name, &palette, layout))
+}
+
+fn rgb(hex: u32) -> Rgb<u8> {
+    Rgb([(hex >> 16) as u8, (hex >> 8) as u8, hex as u8])
+}
+
+// Palettes are listed row by row, left to right, like they are calibrated
+
+const GARTIC_PHONE_COLORS: [u32; 18] = [
+    0x000000, 0x666666, 0x0050CD, //
+    0xFFFFFF, 0xAAAAAA, 0x26C9FF, //
+    0x017420, 0x990000, 0x964112, //
+    0x11B03C, 0xFF0013, 0xFF7829, //
+    0xB0701C, 0x99004E, 0xCB5A57, //
+    0xFFC126, 0xFF008F, 0xFEAFA8, //
+];
+
+const SKRIBBL_IO_COLORS: [u32; 26] = [
+    0xFFFFFF, 0xC1C1C1, 0xEF130B, 0xFF7100, 0xFFE400, 0x00CC00, 0x00FF91, 0x00B2FF, 0x231FD3,
+    0xA300BA, 0xDF69A7, 0xFFAC8E, 0xA0522D, //
+    0x000000, 0x505050, 0x740B07, 0xC23800, 0xE8A200, 0x004619, 0x00785D, 0x00569E, 0x0E0865,
+    0x550069, 0x873554, 0xCC774D, 0x63300D, //
+];
+
+const DRAWIZE_COLORS: [u32; 16] = [
+    0x000000, 0xFFFFFF, 0x7F7F7F, 0xC3C3C3, 0xFF0000, 0xFF8000, 0xFFFF00, 0x00FF00, //
+    0x008000, 0x00FFFF, 0x0000FF, 0x000080, 0xFF00FF, 0x800080, 0xFF80C0, 0x804000, //
+];
+
+const PAINT_COLORS: [u32; 20] = [
+    0x000000, 0x7F7F7F, 0x880015, 0xED1C24, 0xFF7F27, 0xFFF200, 0x22B14C, 0x00A2E8, 0x3F48CC,
+    0xA349A4, //
+    0xFFFFFF, 0xC3C3C3, 0xB97A57, 0xFFAEC9, 0xFFC90E, 0xEFE4B0, 0xB5E61D, 0x99D9EA, 0x7092BE,
+    0xC8BFE7, //
+];
@@ -0,0 +1,228 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rdev::Key;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HotkeyAction {
+    StartDraw,
+    Pause,
+    Abort,
+    ReselectArea,
+    LoadNextImage,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 5] = [
+        HotkeyAction::StartDraw,
+        HotkeyAction::Pause,
+        HotkeyAction::Abort,
+        HotkeyAction::ReselectArea,
+        HotkeyAction::LoadNextImage,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            HotkeyAction::StartDraw => "start_draw",
+            HotkeyAction::Pause => "pause",
+            HotkeyAction::Abort => "abort",
+            HotkeyAction::ReselectArea => "reselect_area",
+            HotkeyAction::LoadNextImage => "load_next_image",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HotkeyBindings {
+    pub start_draw: Key,
+    pub pause: Key,
+    pub abort: Key,
+    pub reselect_area: Key,
+    pub load_next_image: Key,
+}
+
+impl Default for HotkeyBindings {
+    fn default() -> Self {
+        HotkeyBindings {
+            start_draw: Key::F6,
+            pause: Key::F9,
+            abort: Key::Escape,
+            reselect_area: Key::F7,
+            load_next_image: Key::F8,
+        }
+    }
+}
+
+impl HotkeyBindings {
+    pub fn key(&self, action: HotkeyAction) -> Key {
+        match action {
+            HotkeyAction::StartDraw => self.start_draw,
+            HotkeyAction::Pause => self.pause,
+            HotkeyAction::Abort => self.abort,
+            HotkeyAction::ReselectArea => self.reselect_area,
+            HotkeyAction::LoadNextImage => self.load_next_image,
+        }
+    }
+
+    fn key_mut(&mut self, action: HotkeyAction) -> &mut Key {
+        match action {
+            HotkeyAction::StartDraw => &mut self.start_draw,
+            HotkeyAction::Pause => &mut self.pause,
+            HotkeyAction::Abort => &mut self.abort,
+            HotkeyAction::ReselectArea => &mut self.reselect_area,
+            HotkeyAction::LoadNextImage => &mut self.load_next_image,
+        }
+    }
+
+    pub fn action(&self, key: Key) -> Option<HotkeyAction> {
+        HotkeyAction::ALL
+            .into_iter()
+            .find(|&action| self.key(action) == key)
+    }
+}
+
+// Bindings are stored as "action key" lines, e.g. "pause F9".
+// Unknown actions or keys are skipped and keep their default binding
+pub fn load_hotkey_bindings(filename: &str) -> io::Result<HotkeyBindings> {
+    let file = File::open(filename)?;
+    let reader = BufReader::new(file);
+    let mut bindings = HotkeyBindings::default();
+
+    for line in reader.lines() {
+        let line = line?;
+        let mut parts = line.split_whitespace();
+        let (Some(action_name), Some(key_name)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let action = HotkeyAction::ALL
+            .into_iter()
+            .find(|action| action.name() == action_name);
+        if let (Some(action), Some(key)) = (action, parse_key(key_name)) {
+            *bindings.key_mut(action) = key;
+        }
+    }
+    Ok(bindings)
+}
+
+const BINDABLE_KEYS: [Key; 33] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::Escape,
+    Key::Pause,
+    Key::ScrollLock,
+    Key::PrintScreen,
+    Key::Insert,
+    Key::Delete,
+    Key::Home,
+    Key::End,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Space,
+    Key::Tab,
+    Key::Backspace,
+    Key::Return,
+    Key::UpArrow,
+    Key::DownArrow,
+    Key::LeftArrow,
+    Key::RightArrow,
+    Key::KpPlus,
+    Key::KpMinus,
+    Key::KpMultiply,
+];
+
+pub fn parse_key(name: &str) -> Option<Key> {
+    BINDABLE_KEYS
+        .into_iter()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+}
+
+type Subscribers<T> = Arc<Mutex<Vec<Sender<T>>>>;
+
+// One rdev listener for the whole application. Every subscriber gets its own
+// channel and receives all actions; closed channels are dropped on next send
+#[derive(Clone)]
+pub struct HotkeyService {
+    bindings: HotkeyBindings,
+    subscribers: Subscribers<HotkeyAction>,
+    pointer_subscribers: Subscribers<(f64, f64)>,
+}
+
+impl HotkeyService {
+    pub fn start(bindings: HotkeyBindings) -> Self {
+        let service = HotkeyService {
+            bindings,
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            pointer_subscribers: Arc::new(Mutex::new(Vec::new())),
+        };
+
+        let listener = service.clone();
+        thread::spawn(move || {
+            // Holding a key sends repeated KeyPress events, so an action only
+            // fires on the first press after a release
+            let mut pressed: Option<Key> = None;
+            let callback = move |event: rdev::Event| match event.event_type {
+                rdev::EventType::KeyPress(key) if pressed != Some(key) => {
+                    pressed = Some(key);
+                    let action = listener.bindings.action(key);
+                    if let Some(action) = action {
+                        listener.broadcast(action);
+                    }
+                }
+                rdev::EventType::KeyRelease(key) if pressed == Some(key) => pressed = None,
+                rdev::EventType::MouseMove { x, y } => listener.broadcast_pointer((x, y)),
+                _ => {}
+            };
+
+            if let Err(e) = rdev::listen(callback) {
+                eprintln!("Error listening for events: {:?}", e);
+            }
+        });
+
+        service
+    }
+
+    pub fn subscribe(&self) -> Receiver<HotkeyAction> {
+        let (tx, rx) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    // Real pointer positions, including the ones we simulate ourselves
+    pub fn subscribe_pointer(&self) -> Receiver<(f64, f64)> {
+        let (tx, rx) = mpsc::channel();
+        self.pointer_subscribers.lock().unwrap().push(tx);
+        rx
+    }
+
+    // Lets the UI trigger an action as if its key was pressed
+    pub fn trigger(&self, action: HotkeyAction) {
+        self.broadcast(action);
+    }
+
+    fn broadcast(&self, action: HotkeyAction) {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(action).is_ok());
+    }
+
+    fn broadcast_pointer(&self, pos: (f64, f64)) {
+        self.pointer_subscribers
+            .lock()
+            .unwrap()
+            .retain(|tx| tx.send(pos).is_ok());
+    }
+}
@@ -0,0 +1,242 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use druid::{Data, Lens};
+
+// Pixels of pointer travel per intermediate point of a path
+const PATH_STEP: f64 = 20.0;
+const MAX_APPROACH_POINTS: usize = 30;
+const MAX_STROKE_POINTS: usize = 16;
+// How far the control points of an approach curve bend away from the straight
+// line, as a fraction of its length
+const MAX_CURVE: f64 = 0.25;
+
+#[derive(Clone, Data, Lens)]
+pub struct HumanizeConfig {
+    pub enabled: bool,
+    // Random offset of the points between strokes, in pixels
+    pub jitter: f64,
+    // Bounds of the random delay between two input events, in milliseconds
+    pub min_delay: f64,
+    pub max_delay: f64,
+}
+
+impl HumanizeConfig {
+    fn delay_range(&self) -> (f64, f64) {
+        let min = self.min_delay.max(0.0);
+        (min, self.max_delay.max(min))
+    }
+
+    // Average of the random delays, for estimates
+    pub fn mean_delay(&self) -> Duration {
+        let (min, max) = self.delay_range();
+        Duration::from_secs_f64((min + max) / 2.0 / 1000.0)
+    }
+}
+
+impl Default for HumanizeConfig {
+    fn default() -> Self {
+        HumanizeConfig {
+            enabled: false,
+            jitter: 1.5,
+            min_delay: 2.0,
+            max_delay: 8.0,
+        }
+    }
+}
+
+// xorshift64*, good enough for wobbling a pointer
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // The state must never be zero
+        Rng(seed | 1)
+    }
+
+    fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Rng::new(nanos)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniform in [min, max)
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        min + unit * (max - min)
+    }
+}
+
+// Ease in and out: slow at both ends, fastest in the middle
+fn ease(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn point_count(from: (f64, f64), to: (f64, f64), max: usize) -> usize {
+    let distance = (to.0 - from.0).hypot(to.1 - from.1);
+    ((distance / PATH_STEP) as usize).clamp(1, max)
+}
+
+// Pointer moves of an approach, each one after a delay. The position before
+// the first approach to a color is not known, it is assumed to be far away
+pub fn approach_moves(from: Option<(f64, f64)>, to: (f64, f64)) -> usize {
+    from.map_or(MAX_APPROACH_POINTS, |from| {
+        point_count(from, to, MAX_APPROACH_POINTS)
+    })
+}
+
+pub fn stroke_moves(from: (f64, f64), to: (f64, f64)) -> usize {
+    point_count(from, to, MAX_STROKE_POINTS)
+}
+
+// Turns the straight jumps of the plan into pointer paths. Every path ends
+// exactly at its target, and paths inside a stroke never leave the stroke
+pub struct Humanizer {
+    config: HumanizeConfig,
+    rng: Rng,
+}
+
+impl Humanizer {
+    pub fn new(config: HumanizeConfig) -> Self {
+        Self::with_rng(config, Rng::from_time())
+    }
+
+    fn with_rng(config: HumanizeConfig, rng: Rng) -> Self {
+        Humanizer { config, rng }
+    }
+
+    pub fn delay(&mut self) -> Duration {
+        let (min, max) = self.config.delay_range();
+        Duration::from_secs_f64(self.rng.range(min, max) / 1000.0)
+    }
+
+    // A curved path with jitter from where the pointer is to where the next
+    // stroke or button is, `to` included
+    pub fn approach(&mut self, from: (f64, f64), to: (f64, f64)) -> Vec<(f64, f64)> {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        // Both control points bend to the same side of the line
+        let bend = self.rng.range(-MAX_CURVE, MAX_CURVE);
+        let normal = (-dy * bend, dx * bend);
+        let control1 = (from.0 + dx / 3.0 + normal.0, from.1 + dy / 3.0 + normal.1);
+        let control2 = (
+            from.0 + dx * 2.0 / 3.0 + normal.0,
+            from.1 + dy * 2.0 / 3.0 + normal.1,
+        );
+
+        let count = point_count(from, to, MAX_APPROACH_POINTS);
+        let mut path: Vec<(f64, f64)> = (1..count)
+            .map(|i| {
+                let t = ease(i as f64 / count as f64);
+                let u = 1.0 - t;
+                let x = u * u * u * from.0
+                    + 3.0 * u * u * t * control1.0
+                    + 3.0 * u * t * t * control2.0
+                    + t * t * t * to.0;
+                let y = u * u * u * from.1
+                    + 3.0 * u * u * t * control1.1
+                    + 3.0 * u * t * t * control2.1
+                    + t * t * t * to.1;
+                let jitter = self.config.jitter;
+                (
+                    x + self.rng.range(-jitter, jitter),
+                    y + self.rng.range(-jitter, jitter),
+                )
+            })
+            .collect();
+        path.push(to);
+        path
+    }
+
+    // Points along a stroke with eased spacing and no jitter, `to` included
+    pub fn stroke(&mut self, from: (f64, f64), to: (f64, f64)) -> Vec<(f64, f64)> {
+        let count = point_count(from, to, MAX_STROKE_POINTS);
+        (1..=count)
+            .map(|i| {
+                let t = ease(i as f64 / count as f64);
+                (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGETS: [((f64, f64), (f64, f64)); 4] = [
+        ((0.0, 0.0), (5.0, 3.0)),
+        ((100.0, 50.0), (400.0, 250.0)),
+        ((800.0, 600.0), (10.0, 20.0)),
+        ((-300.0, 40.0), (1500.0, 40.0)),
+    ];
+
+    fn humanizer(seed: u64) -> Humanizer {
+        let config = HumanizeConfig {
+            enabled: true,
+            ..HumanizeConfig::default()
+        };
+        Humanizer::with_rng(config, Rng::new(seed))
+    }
+
+    #[test]
+    fn approach_ends_exactly_on_the_target() {
+        for seed in 1..20 {
+            let mut humanizer = humanizer(seed);
+            for (from, to) in TARGETS {
+                let path = humanizer.approach(from, to);
+                assert_eq!(path.last(), Some(&to));
+                assert_eq!(path.len(), approach_moves(Some(from), to));
+            }
+        }
+    }
+
+    #[test]
+    fn stroke_points_stay_on_the_segment() {
+        for seed in 1..20 {
+            let mut humanizer = humanizer(seed);
+            for (from, to) in TARGETS {
+                let path = humanizer.stroke(from, to);
+                assert_eq!(path.last(), Some(&to));
+                assert_eq!(path.len(), stroke_moves(from, to));
+
+                let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                let length_squared = dx * dx + dy * dy;
+                let mut previous = 0.0;
+                for (x, y) in path {
+                    // Distance from the line and position along it
+                    let cross = (x - from.0) * dy - (y - from.1) * dx;
+                    assert!(cross.abs() / length_squared.sqrt() < 1e-9);
+                    let along = ((x - from.0) * dx + (y - from.1) * dy) / length_squared;
+                    assert!(along > previous && along <= 1.0 + 1e-12);
+                    previous = along;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn delays_stay_in_range() {
+        let mut humanizer = humanizer(7);
+        let (min, max) = humanizer.config.delay_range();
+        for _ in 0..1000 {
+            let delay = humanizer.delay().as_secs_f64() * 1000.0;
+            assert!(delay >= min && delay <= max, "{} ms", delay);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_path() {
+        let (from, to) = TARGETS[1];
+        assert_eq!(
+            humanizer(42).approach(from, to),
+            humanizer(42).approach(from, to)
+        );
+    }
+}
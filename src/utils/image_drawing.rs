@@ -1,170 +1,216 @@
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
-    thread, time,
+    fmt,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    thread,
+    time::{self, Duration, Instant},
 };
 
 use rdev::{self, simulate};
 
-use druid::image::{ImageBuffer, Pixel, Rgb};
+use crate::error::BotError;
+
+use super::checkpoint::{clear_checkpoint, Checkpoint};
+use super::draw_plan::{count_missing, DrawPlan, Line};
+use super::fail_safe::{FailSafe, FailSafeTrip};
+use super::hotkeys::HotkeyAction;
+use super::humanize::{HumanizeConfig, Humanizer};
+use super::image_utils::color_hex;
+use super::screen_source::ScreenSource;
+use super::screen_space::ScreenSpace;
+use super::time_estimate::DrawTimings;
+
+use druid::image::{ImageBuffer, Rgb};
+use druid::Point;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DrawingState {
+    Running,
+    Paused,
+    Aborted,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DrawProgress {
+    pub strokes_done: usize,
+    pub strokes_total: usize,
+    // Strokes that were already done by an earlier run when this one started
+    pub strokes_resumed: usize,
+    pub color: Rgb<u8>,
+    pub elapsed: Duration,
+}
+
+impl DrawProgress {
+    pub fn fraction(&self) -> f64 {
+        if self.strokes_total == 0 {
+            return 1.0;
+        }
+        self.strokes_done as f64 / self.strokes_total as f64
+    }
+
+    // Extrapolated from the average time per stroke so far
+    pub fn eta(&self) -> Option<Duration> {
+        let strokes_drawn = self.strokes_done.saturating_sub(self.strokes_resumed);
+        if strokes_drawn == 0 {
+            return None;
+        }
+        let per_stroke = self.elapsed.as_secs_f64() / strokes_drawn as f64;
+        let remaining = self.strokes_total.saturating_sub(self.strokes_done);
+        Some(Duration::from_secs_f64(per_stroke * remaining as f64))
+    }
+}
+
+impl fmt::Display for DrawProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} strokes, color {}\n{} elapsed",
+            self.strokes_done,
+            self.strokes_total,
+            color_hex(&self.color),
+            format_duration(self.elapsed)
+        )?;
+        if let Some(eta) = self.eta() {
+            write!(f, ", ETA {}", format_duration(eta))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[derive(Debug)]
+pub enum DrawOutcome {
+    // `verified` is None without verification, otherwise whether the last
+    // screenshot showed no missing pixels
+    Completed {
+        timings: DrawTimings,
+        verified: Option<bool>,
+    },
+    Aborted,
+    FailSafe(FailSafeTrip),
+    Failed(BotError),
+}
+
+impl fmt::Display for DrawOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawOutcome::Completed { verified: None, .. } => f.write_str("Drawing finished"),
+            DrawOutcome::Completed {
+                verified: Some(true),
+                ..
+            } => f.write_str("Drawing finished, the screenshot shows every pixel"),
+            DrawOutcome::Completed {
+                verified: Some(false),
+                ..
+            } => f.write_str(
+                "Drawing finished but not verified, the screenshot file wasn't written again or still misses pixels",
+            ),
+            DrawOutcome::Aborted => f.write_str("Drawing aborted"),
+            DrawOutcome::FailSafe(trip) => write!(f, "Drawing aborted by fail-safe: {}", trip),
+            DrawOutcome::Failed(e) => write!(f, "Drawing failed: {}", e),
+        }
+    }
+}
+
+// Progress is reported at most this often, plus on every color change
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// Pause after every stroke so the game keeps up with the input
+pub const STROKE_DELAY: Duration = Duration::from_millis(5);
+// Rough cost of sending the input events of a stroke or a color change
+pub const INPUT_COST: Duration = Duration::from_millis(2);
+const CAPTURE_DELAY: Duration = Duration::from_millis(500);
 
 struct DrawingBot {
-    image: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    plan: DrawPlan,
     start_position: (f64, f64),
     colors_coordinates: HashMap<Rgb<u8>, (f64, f64)>,
-    pixels_lines_to_draw: HashMap<Rgb<u8>, Vec<((f64, f64), (f64, f64))>>,
-    drawing: Arc<Mutex<bool>>,
+    hotkeys: Receiver<HotkeyAction>,
+    fail_safe: FailSafe,
+    state: DrawingState,
+    fail_safe_trip: Option<FailSafeTrip>,
+    timings: DrawTimings,
+    checkpoint: Checkpoint,
+    // Off for correction plans, a checkpoint refers to the main plan
+    save_checkpoints: bool,
+    // Area coordinates of the previous stroke of the current color
+    last_stroke_end: Option<(f64, f64)>,
+    humanizer: Option<Humanizer>,
+    // Humanize delays waited so far
+    delays: usize,
+    // Physical screen position of the last synthesized pointer move
+    pointer: Option<(f64, f64)>,
+    screen: ScreenSpace,
 }
 
 impl DrawingBot {
-    fn new(
-        image: ImageBuffer<Rgb<u8>, Vec<u8>>,
-        colors_coordinates: HashMap<Rgb<u8>, (f64, f64)>,
-        start_position: (f64, f64),
-        pixels_interval: u8,
-    ) -> Self {
-        let dummy_bot = Self {
-            image: image.clone(),
-            start_position,
-            colors_coordinates: colors_coordinates.clone(),
-            pixels_lines_to_draw: HashMap::new(),
-            drawing: Arc::new(Mutex::new(true)),
-        };
-
-        let pixels_lines_to_draw = dummy_bot.extract_pixel_lines_to_draw(pixels_interval);
-
+    fn new(job: DrawJob, hotkeys: Receiver<HotkeyAction>, fail_safe: FailSafe) -> Self {
+        let humanize = job.humanize;
         Self {
-            image,
-            start_position,
-            colors_coordinates: colors_coordinates,
-            pixels_lines_to_draw: pixels_lines_to_draw,
-            drawing: Arc::new(Mutex::new(true)),
-        }
-    }
-
-    fn extract_pixel_lines_to_draw(
-        &self,
-        pixels_interval: u8,
-    ) -> HashMap<Rgb<u8>, Vec<((f64, f64), (f64, f64))>> {
-        let (draw_vertically_lines, nb_vertical_lines) =
-            self.extract_lines_to_draw(true, pixels_interval);
-        let (draw_horizontally_lines, nb_horizontal_lines) =
-            self.extract_lines_to_draw(false, pixels_interval);
-
-        if nb_vertical_lines > nb_horizontal_lines {
-            let mut vec: Vec<(Rgb<u8>, Vec<((f64, f64), (f64, f64))>)> =
-                draw_horizontally_lines.into_iter().collect();
-            vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
-            HashMap::from_iter(vec.into_iter())
-        } else {
-            let mut vec: Vec<(Rgb<u8>, Vec<((f64, f64), (f64, f64))>)> =
-                draw_vertically_lines.into_iter().collect();
-            vec.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
-            HashMap::from_iter(vec.into_iter())
-        }
-    }
-
-    fn extract_lines_to_draw(
-        &self,
-        vertically: bool,
-        pixels_interval: u8,
-    ) -> (HashMap<Rgb<u8>, Vec<((f64, f64), (f64, f64))>>, i32) {
-        let (width, height) = self.image.dimensions();
-        let (w, h) = if vertically {
-            (width, height)
-        } else {
-            (height, width)
-        };
-
-        let mut lines: HashMap<Rgb<u8>, Vec<((f64, f64), (f64, f64))>> = HashMap::new();
-        let mut nb_lines = 0;
-
-        for x in (0..w).step_by(pixels_interval as usize) {
-            let mut line_color: Option<Rgb<u8>> = None;
-            let mut line_start: (f64, f64) = (0.0, 0.0);
-            let mut line_end: (f64, f64) = (0.0, 0.0);
-
-            for y in (0..h).step_by(pixels_interval as usize) {
-                let (pixel, current_position) = if vertically {
-                    let pixel = self.image.get_pixel(x, y).to_rgb();
-                    (
-                        pixel,
-                        (
-                            self.start_position.0 + x as f64,
-                            self.start_position.1 + y as f64,
-                        ),
-                    )
+            plan: job.plan,
+            start_position: job.start_pos,
+            colors_coordinates: job.colors_coordinates,
+            hotkeys,
+            fail_safe,
+            state: DrawingState::Running,
+            fail_safe_trip: None,
+            timings: DrawTimings {
+                mean_delay: if humanize.enabled {
+                    humanize.mean_delay().as_secs_f64()
                 } else {
-                    let pixel = self.image.get_pixel(y, x).to_rgb();
-                    (
-                        pixel,
-                        (
-                            self.start_position.0 + y as f64,
-                            self.start_position.1 + x as f64,
-                        ),
-                    )
-                };
-
-                if line_color.is_none() {
-                    line_color = Some(pixel);
-                    line_start = current_position;
-                } else if let Some(lc) = line_color {
-                    if lc != pixel {
-                        if lc != Rgb([255, 255, 255]) {
-                            nb_lines += 1;
-                        }
-                        lines
-                            .entry(lc)
-                            .or_insert_with(Vec::new)
-                            .push((line_start, line_end));
-
-                        line_color = Some(pixel);
-                        line_start = current_position;
-                    }
-                }
-                line_end = current_position;
-            }
-
-            if let Some(lc) = line_color {
-                if lc != Rgb([255, 255, 255]) {
-                    nb_lines += 1;
-                }
-                lines
-                    .entry(lc)
-                    .or_insert_with(Vec::new)
-                    .push((line_start, line_end));
-            }
+                    0.0
+                },
+                ..DrawTimings::default()
+            },
+            checkpoint: job.checkpoint,
+            save_checkpoints: true,
+            last_stroke_end: None,
+            humanizer: humanize.enabled.then(|| Humanizer::new(humanize)),
+            delays: 0,
+            pointer: None,
+            screen: job.screen,
         }
-
-        (lines, nb_lines)
     }
 
-    fn change_color(&self, color: Rgb<u8>) {
-        let coordinate = self.colors_coordinates.get(&color).unwrap();
-        self.mouse_simulation(&rdev::EventType::MouseMove {
-            x: coordinate.0,
-            y: coordinate.1,
-        });
-        self.mouse_simulation(&rdev::EventType::ButtonPress(rdev::Button::Left));
-        self.mouse_simulation(&rdev::EventType::ButtonRelease(rdev::Button::Left))
+    fn change_color(&mut self, color: Rgb<u8>) -> Result<(), BotError> {
+        let coordinate = *self
+            .colors_coordinates
+            .get(&color)
+            .ok_or(BotError::MissingColorPosition(color))?;
+        self.approach(self.physical(coordinate))?;
+        self.button(rdev::EventType::ButtonPress(rdev::Button::Left))?;
+        self.button(rdev::EventType::ButtonRelease(rdev::Button::Left))
         // self.enigo
         //     .move_mouse(coordinate.0 as i32, coordinate.1 as i32, Abs)
         //     .unwrap();
         // self.enigo.button(Button::Left, Click).unwrap();
     }
 
-    fn draw_line(&mut self, line: ((f64, f64), (f64, f64))) {
-        self.mouse_simulation(&rdev::EventType::MouseMove {
-            x: line.0 .0,
-            y: line.0 .1,
-        });
-        self.mouse_simulation(&rdev::EventType::ButtonPress(rdev::Button::Left));
+    fn draw_line(&mut self, line: Line) -> Result<(), BotError> {
+        let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+        self.timings.travel += self
+            .last_stroke_end
+            .map_or(0.0, |last| distance(last, line.0))
+            + distance(line.0, line.1);
+        self.last_stroke_end = Some(line.1);
+
+        let (x0, y0) = self.start_position;
+        let start = self.physical((x0 + line.0 .0, y0 + line.0 .1));
+        let end = self.physical((x0 + line.1 .0, y0 + line.1 .1));
+        self.approach(start)?;
+        self.button(rdev::EventType::ButtonPress(rdev::Button::Left))?;
 
-        self.mouse_simulation(&rdev::EventType::MouseMove {
-            x: line.1 .0,
-            y: line.1 .1,
-        });
-        self.mouse_simulation(&rdev::EventType::ButtonRelease(rdev::Button::Left));
+        // Inside a stroke the pointer only follows the line, jitter would
+        // paint outside of it
+        let path = match &mut self.humanizer {
+            Some(humanizer) => humanizer.stroke(start, end),
+            None => vec![end],
+        };
+        self.move_along(path)?;
+        self.button(rdev::EventType::ButtonRelease(rdev::Button::Left))?;
         // self.enigo
         //     .move_mouse(line.0 .0 as i32, line.0 .1 as i32, Abs)
         //     .unwrap();
@@ -174,59 +220,286 @@ impl DrawingBot {
         //     .move_mouse(line.1 .0 as i32, line.1 .1 as i32, Abs)
         //     .unwrap();
         // self.enigo.button(Button::Left, Release).unwrap();
+        Ok(())
     }
 
-    fn stop_drawing(&self) {
-        let drawing = Arc::clone(&self.drawing);
-        thread::spawn(move || {
-            let callback = move |event: rdev::Event| match event.event_type {
-                rdev::EventType::KeyPress(key) => {
-                    if key == rdev::Key::Escape {
-                        let mut is_drawing = drawing.lock().unwrap();
-                        *is_drawing = false;
-                    }
-                }
-                _ => {}
-            };
+    fn physical(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        self.screen.to_physical(Point::new(x, y)).into()
+    }
+
+    // Moves the pointer to `to` with no button held
+    fn approach(&mut self, to: (f64, f64)) -> Result<(), BotError> {
+        let path = match (&mut self.humanizer, self.pointer) {
+            (Some(humanizer), Some(from)) => humanizer.approach(from, to),
+            _ => vec![to],
+        };
+        self.move_along(path)
+    }
 
-            if let Err(e) = rdev::listen(callback) {
-                eprintln!("Error listening for events: {:?}", e);
+    fn move_along(&mut self, path: Vec<(f64, f64)>) -> Result<(), BotError> {
+        for (x, y) in path {
+            self.humanize_delay();
+            self.mouse_simulation(&rdev::EventType::MouseMove { x, y })?;
+        }
+        Ok(())
+    }
+
+    // Presses and releases wait like pointer moves, so clicks aren't perfectly regular
+    fn button(&mut self, event_type: rdev::EventType) -> Result<(), BotError> {
+        self.humanize_delay();
+        self.mouse_simulation(&event_type)
+    }
+
+    fn humanize_delay(&mut self) {
+        if let Some(humanizer) = &mut self.humanizer {
+            thread::sleep(humanizer.delay());
+            self.delays += 1;
+        }
+    }
+
+    fn apply_hotkey(&mut self, action: HotkeyAction) {
+        self.state = match (action, self.state) {
+            (HotkeyAction::Abort, _) => DrawingState::Aborted,
+            (HotkeyAction::Pause, DrawingState::Running) => DrawingState::Paused,
+            (HotkeyAction::Pause, DrawingState::Paused) => DrawingState::Running,
+            (_, state) => state,
+        };
+    }
+
+    // Blocks while drawing is paused. Returns false once drawing is aborted
+    fn wait_while_paused(&mut self, color: Rgb<u8>) -> Result<bool, BotError> {
+        while let Ok(action) = self.hotkeys.try_recv() {
+            self.apply_hotkey(action);
+        }
+        if self.state == DrawingState::Running {
+            if let Err(trip) = self.fail_safe.check() {
+                self.fail_safe_trip = Some(trip);
+                self.state = DrawingState::Aborted;
             }
-        });
+        }
+
+        let mut was_paused = false;
+        loop {
+            match self.state {
+                DrawingState::Running => break,
+                DrawingState::Paused => was_paused = true,
+                DrawingState::Aborted => return Ok(false),
+            }
+            match self.hotkeys.recv_timeout(time::Duration::from_millis(50)) {
+                Ok(action) => self.apply_hotkey(action),
+                Err(RecvTimeoutError::Timeout) => (),
+                // The hotkey service is gone, nothing can resume us anymore
+                Err(RecvTimeoutError::Disconnected) => return Ok(false),
+            }
+        }
+
+        // The user may have moved the mouse or picked another color while paused
+        if was_paused {
+            self.fail_safe.reset();
+            self.pointer = None;
+            self.change_color(color)?;
+        }
+        Ok(true)
+    }
+
+    fn outcome(&self) -> DrawOutcome {
+        match (self.state, self.fail_safe_trip) {
+            (_, Some(trip)) => DrawOutcome::FailSafe(trip),
+            (DrawingState::Aborted, None) => DrawOutcome::Aborted,
+            _ => DrawOutcome::Completed {
+                timings: self.timings,
+                verified: None,
+            },
+        }
     }
 
-    fn draw(&mut self) {
-        &self.stop_drawing();
-        for (color, lines) in self.pixels_lines_to_draw.clone() {
-            if color != Rgb([255, 255, 255]) {
-                self.change_color(color);
-                for line in lines {
-                    if !*self.drawing.lock().unwrap() {
-                        break;
+    fn draw(
+        &mut self,
+        on_progress: &mut impl FnMut(DrawProgress),
+        verification: Option<Verification>,
+    ) -> DrawOutcome {
+        let mut outcome = match self.draw_plan(on_progress) {
+            Ok(()) => self.outcome(),
+            Err(e) => DrawOutcome::Failed(e),
+        };
+        // Best effort, without a checkpoint drawing can still be restarted
+        let _ = match outcome {
+            DrawOutcome::Completed { .. } => clear_checkpoint(),
+            _ => self.checkpoint.save(),
+        };
+
+        let Some(mut verification) = verification else {
+            return outcome;
+        };
+        // Corrections are single pixels, their timings would skew the estimate
+        let measured = self.timings;
+        self.save_checkpoints = false;
+        let mut previous_missing = usize::MAX;
+        let mut verified = false;
+        for _ in 0..verification.rounds {
+            if !matches!(outcome, DrawOutcome::Completed { .. }) {
+                break;
+            }
+            // Let the game catch up with the last strokes before looking at it
+            thread::sleep(CAPTURE_DELAY);
+            let capture = match verification.source.capture() {
+                Ok(Some(capture)) => capture,
+                Ok(None) => break,
+                Err(e) => return DrawOutcome::Failed(e),
+            };
+            // Pixels that stay missing can't be drawn, e.g. because they are
+            // under something or the capture is stale
+            let missing = count_missing(&verification.expected, &capture);
+            if missing == 0 {
+                verified = true;
+                break;
+            }
+            if missing >= previous_missing {
+                break;
+            }
+            previous_missing = missing;
+
+            self.plan = DrawPlan::missing(&verification.expected, &capture);
+            self.checkpoint = Checkpoint::start(&self.plan);
+            outcome = match self.draw_plan(on_progress) {
+                Ok(()) => self.outcome(),
+                Err(e) => DrawOutcome::Failed(e),
+            };
+        }
+        match outcome {
+            DrawOutcome::Completed { .. } => DrawOutcome::Completed {
+                timings: measured,
+                verified: Some(verified),
+            },
+            outcome => outcome,
+        }
+    }
+
+    fn draw_plan(&mut self, on_progress: &mut impl FnMut(DrawProgress)) -> Result<(), BotError> {
+        let started = Instant::now();
+        let strokes_total = self.plan.stroke_count();
+        let resume = self.checkpoint;
+        let strokes_resumed = resume.strokes_done(&self.plan);
+        let mut strokes_done = strokes_resumed;
+        let mut last_report = started;
+
+        self.fail_safe.reset();
+        let colors = self.plan.colors.clone();
+        for (color_index, strokes) in colors.into_iter().enumerate().skip(resume.color_index) {
+            let color = strokes.color;
+            let skip = if color_index == resume.color_index {
+                resume.stroke_index
+            } else {
+                0
+            };
+            self.checkpoint.color_index = color_index;
+            self.checkpoint.stroke_index = skip;
+            on_progress(DrawProgress {
+                strokes_done,
+                strokes_total,
+                strokes_resumed,
+                color,
+                elapsed: started.elapsed(),
+            });
+            if !self.wait_while_paused(color)? {
+                return Ok(());
+            }
+            let color_started = Instant::now();
+            let delays = self.delays;
+            self.change_color(color)?;
+            self.timings.color_time += color_started.elapsed().as_secs_f64();
+            self.timings.colors += 1;
+            self.timings.color_delays += self.delays - delays;
+            self.last_stroke_end = None;
+            for line in strokes.lines.into_iter().skip(skip) {
+                if !self.wait_while_paused(color)? {
+                    return Ok(());
+                }
+                let stroke_started = Instant::now();
+                let delays = self.delays;
+                self.draw_line(line)?;
+                strokes_done += 1;
+                self.checkpoint.stroke_index += 1;
+                thread::sleep(STROKE_DELAY);
+                self.timings.stroke_time += stroke_started.elapsed().as_secs_f64();
+                self.timings.strokes += 1;
+                self.timings.stroke_delays += self.delays - delays;
+
+                if last_report.elapsed() >= PROGRESS_INTERVAL {
+                    last_report = Instant::now();
+                    // Survives a crash of the game or the bot, not only an abort
+                    if self.save_checkpoints {
+                        let _ = self.checkpoint.save();
                     }
-                    self.draw_line(line);
-                    thread::sleep(time::Duration::from_millis(5))
+                    on_progress(DrawProgress {
+                        strokes_done,
+                        strokes_total,
+                        strokes_resumed,
+                        color,
+                        elapsed: started.elapsed(),
+                    });
                 }
             }
         }
+        // The last report may be up to PROGRESS_INTERVAL old
+        if let Some(last) = self.plan.colors.last() {
+            on_progress(DrawProgress {
+                strokes_done,
+                strokes_total,
+                strokes_resumed,
+                color: last.color,
+                elapsed: started.elapsed(),
+            });
+        }
+        Ok(())
     }
 
-    fn mouse_simulation(&self, event_type: &rdev::EventType) {
-        simulate(event_type).unwrap();
+    // Pointer moves are in physical pixels, like the pointer reports of the fail-safe
+    fn mouse_simulation(&mut self, event_type: &rdev::EventType) -> Result<(), BotError> {
+        if let rdev::EventType::MouseMove { x, y } = *event_type {
+            self.fail_safe.record((x, y));
+            self.pointer = Some((x, y));
+            let input = self.screen.to_input(Point::new(x, y));
+            simulate(&rdev::EventType::MouseMove {
+                x: input.x,
+                y: input.y,
+            })?;
+            return Ok(());
+        }
+        simulate(event_type)?;
+        Ok(())
     }
 }
 
+// After drawing, the canvas is compared with `expected` and the pixels that
+// are still missing are drawn again, up to `rounds` times or until a round
+// doesn't get any closer
+pub struct Verification {
+    pub source: Box<dyn ScreenSource>,
+    pub expected: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    pub rounds: u32,
+}
+
+pub struct DrawJob {
+    pub plan: DrawPlan,
+    pub colors_coordinates: HashMap<Rgb<u8>, (f64, f64)>,
+    pub start_pos: (f64, f64),
+    // Strokes before it are skipped, and the checkpoint file follows the progress
+    pub checkpoint: Checkpoint,
+    pub verification: Option<Verification>,
+    pub humanize: HumanizeConfig,
+    // Maps the logical coordinates of the job to the pixels sent to the input backend
+    pub screen: ScreenSpace,
+}
+
+// Blocks until the plan is drawn or aborted, so it should run on a worker thread
 pub fn draw_image(
-    image: &ImageBuffer<Rgb<u8>, Vec<u8>>,
-    colors_coordinates: &HashMap<Rgb<u8>, (f64, f64)>,
-    start_pos: &(f64, f64),
-    pixel_interval: u8,
-) {
-    let mut bot = DrawingBot::new(
-        image.clone(),
-        colors_coordinates.clone(),
-        (start_pos.0, start_pos.1),
-        pixel_interval,
-    );
-    bot.draw();
+    mut job: DrawJob,
+    hotkeys: Receiver<HotkeyAction>,
+    fail_safe: FailSafe,
+    mut on_progress: impl FnMut(DrawProgress),
+) -> DrawOutcome {
+    let verification = job.verification.take();
+    let mut bot = DrawingBot::new(job, hotkeys, fail_safe);
+    bot.draw(&mut on_progress, verification)
 }
@@ -0,0 +1,102 @@
+use druid::image::{ImageBuffer, Rgb};
+
+// SSIM is computed over windows of this size, moved by half a window
+const SSIM_WINDOW: u32 = 8;
+const SSIM_C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+const SSIM_C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+// Peak signal-to-noise ratio in dB over all channels, infinite for identical images
+pub fn psnr(a: &ImageBuffer<Rgb<u8>, Vec<u8>>, b: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> f64 {
+    let squared_error: f64 = a
+        .as_raw()
+        .iter()
+        .zip(b.as_raw())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = squared_error / a.as_raw().len().max(1) as f64;
+    if mse == 0.0 {
+        return f64::INFINITY;
+    }
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+// Mean structural similarity of the luma of two images of the same size,
+// 1.0 for identical images
+pub fn ssim(a: &ImageBuffer<Rgb<u8>, Vec<u8>>, b: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> f64 {
+    let (width, height) = a.dimensions();
+    if width < SSIM_WINDOW || height < SSIM_WINDOW || a.dimensions() != b.dimensions() {
+        return if a == b { 1.0 } else { 0.0 };
+    }
+    let luma = |pixel: &Rgb<u8>| {
+        0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64
+    };
+
+    let step = SSIM_WINDOW / 2;
+    let count = (SSIM_WINDOW * SSIM_WINDOW) as f64;
+    let mut total = 0.0;
+    let mut windows = 0;
+    for y in (0..=height - SSIM_WINDOW).step_by(step as usize) {
+        for x in (0..=width - SSIM_WINDOW).step_by(step as usize) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+                (0.0, 0.0, 0.0, 0.0, 0.0);
+            for wy in y..y + SSIM_WINDOW {
+                for wx in x..x + SSIM_WINDOW {
+                    let (la, lb) = (luma(a.get_pixel(wx, wy)), luma(b.get_pixel(wx, wy)));
+                    sum_a += la;
+                    sum_b += lb;
+                    sum_aa += la * la;
+                    sum_bb += lb * lb;
+                    sum_ab += la * lb;
+                }
+            }
+            let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+            let variance_a = sum_aa / count - mean_a * mean_a;
+            let variance_b = sum_bb / count - mean_b * mean_b;
+            let covariance = sum_ab / count - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + SSIM_C1) * (2.0 * covariance + SSIM_C2))
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1)
+                    * (variance_a + variance_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    total / windows as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(32, 24, |x, y| Rgb([(x * 8) as u8, (y * 10) as u8, 128]))
+    }
+
+    #[test]
+    fn identical_images() {
+        let image = gradient();
+        assert_eq!(psnr(&image, &image), f64::INFINITY);
+        assert!((ssim(&image, &image) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn more_noise_scores_lower() {
+        let image = gradient();
+        let mut slightly_off = image.clone();
+        slightly_off.put_pixel(3, 3, Rgb([0, 0, 0]));
+        let inverted = ImageBuffer::from_fn(32, 24, |x, y| {
+            let Rgb([r, g, b]) = *image.get_pixel(x, y);
+            Rgb([255 - r, 255 - g, 255 - b])
+        });
+
+        assert!(psnr(&image, &slightly_off) > psnr(&image, &inverted));
+        assert!(ssim(&image, &slightly_off) < 1.0);
+        assert!(ssim(&image, &slightly_off) > ssim(&image, &inverted));
+    }
+
+    #[test]
+    fn psnr_of_a_known_error() {
+        let black = ImageBuffer::from_pixel(4, 4, Rgb([0, 0, 0]));
+        let white = ImageBuffer::from_pixel(4, 4, Rgb([255, 255, 255]));
+        assert!(psnr(&black, &white).abs() < 1e-9);
+    }
+}
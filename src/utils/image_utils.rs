@@ -1,8 +1,15 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use druid::image::{
     imageops::colorops::{dither, ColorMap},
-    imageops::FilterType::Lanczos3,
-    DynamicImage, ImageBuffer, Rgb,
+    imageops::{
+        self,
+        FilterType::{Lanczos3, Nearest},
+    },
+    DynamicImage, ImageBuffer, ImageFormat, Rgb,
 };
+use druid::Rect;
 
 pub struct Palette {
     colors: Vec<Rgb<u8>>,
@@ -24,7 +31,7 @@ impl ColorMap for Palette {
     fn map_color(&self, pixel: &mut Self::Color) {
         let old_color = *pixel;
         let mut closest_color = &self.colors[0];
-        let mut min_distance = color_distance(&old_color, &closest_color);
+        let mut min_distance = color_distance(&old_color, closest_color);
 
         for color in &self.colors {
             let distance = color_distance(&old_color, color);
@@ -39,22 +46,57 @@ impl ColorMap for Palette {
     }
 }
 
+// Cuts `rect`, in pixels, out of a screenshot and scales it to `size`
+pub fn crop_screenshot(
+    screenshot: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    rect: Rect,
+    size: (u32, u32),
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let rect = rect.intersect(Rect::new(
+        0.0,
+        0.0,
+        screenshot.width() as f64,
+        screenshot.height() as f64,
+    ));
+    let cropped = imageops::crop_imm(
+        screenshot,
+        rect.x0 as u32,
+        rect.y0 as u32,
+        rect.width() as u32,
+        rect.height() as u32,
+    )
+    .to_image();
+    imageops::resize(&cropped, size.0, size.1, Nearest)
+}
+
+// Fits the image into the drawing area, keeping its proportions
+pub fn resize_to_area(img: &DynamicImage, size: (u32, u32)) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    img.resize(size.0, size.1, Lanczos3).to_rgb8()
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Dithering {
+    None,
+    FloydSteinberg,
+    // Spreads only 3/4 of the error, keeps more contrast on small palettes
+    Atkinson,
+}
+
 pub fn quantize(
-    img: &DynamicImage,
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
     palette: &[Rgb<u8>],
-    size: (u32, u32),
-    dithering: bool,
+    dithering: Dithering,
 ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
-    let img = img.resize(size.0, size.1, Lanczos3);
-    let mut img = img.to_rgb8();
+    let mut img = img.clone();
 
     match dithering {
-        true => {
+        Dithering::FloydSteinberg => {
             dither(&mut img, &Palette::from_colors(palette.to_vec()));
 
             img
         }
-        false => {
+        Dithering::Atkinson => atkinson_dither(&img, palette),
+        Dithering::None => {
             let mut quantized_img = ImageBuffer::new(img.width(), img.height());
 
             for (x, y, pixel) in img.enumerate_pixels() {
@@ -68,6 +110,40 @@ pub fn quantize(
     }
 }
 
+fn atkinson_dither(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    palette: &[Rgb<u8>],
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let (width, height) = img.dimensions();
+    let mut pixels: Vec<[f64; 3]> = img
+        .pixels()
+        .map(|pixel| pixel.0.map(|channel| channel as f64))
+        .collect();
+    let mut dithered = ImageBuffer::new(width, height);
+
+    for y in 0..height {
+        for x in 0..width {
+            let old = pixels[(y * width + x) as usize].map(|channel| channel.clamp(0.0, 255.0));
+            let new = find_nearest_color(&Rgb(old.map(|channel| channel.round() as u8)), palette);
+            dithered.put_pixel(x, y, new);
+
+            // An eighth of the error goes to each of six neighbours
+            let error: [f64; 3] = std::array::from_fn(|c| (old[c] - new[c] as f64) / 8.0);
+            for (dx, dy) in [(1, 0), (2, 0), (-1, 1), (0, 1), (1, 1), (0, 2)] {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                    continue;
+                }
+                let neighbour = &mut pixels[(ny * width as i64 + nx) as usize];
+                for c in 0..3 {
+                    neighbour[c] += error[c];
+                }
+            }
+        }
+    }
+    dithered
+}
+
 fn find_nearest_color(pixel: &Rgb<u8>, palette: &[Rgb<u8>]) -> Rgb<u8> {
     let mut nearest_color = palette[0];
     let mut nearest_distance = color_distance(pixel, &nearest_color);
@@ -83,9 +159,42 @@ fn find_nearest_color(pixel: &Rgb<u8>, palette: &[Rgb<u8>]) -> Rgb<u8> {
     nearest_color
 }
 
-fn color_distance(c1: &Rgb<u8>, c2: &Rgb<u8>) -> f64 {
+pub fn color_distance(c1: &Rgb<u8>, c2: &Rgb<u8>) -> f64 {
     let r_diff = c1[0] as f64 - c2[0] as f64;
     let g_diff = c1[1] as f64 - c2[1] as f64;
     let b_diff = c1[2] as f64 - c2[2] as f64;
     (r_diff * r_diff + g_diff * g_diff + b_diff * b_diff).sqrt()
 }
+
+// Returns the image that follows `current` in its directory, wrapping around
+// to the first one after the last
+pub fn next_image_path(current: &Path) -> Option<PathBuf> {
+    let directory = current.parent()?;
+    let mut images: Vec<PathBuf> = fs::read_dir(directory)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| ImageFormat::from_path(path).is_ok())
+        .collect();
+    images.sort();
+
+    let next = images
+        .iter()
+        .position(|path| path == current)
+        .map_or(0, |index| (index + 1) % images.len());
+    images.get(next).cloned()
+}
+
+pub fn color_hex(color: &Rgb<u8>) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+// Accepts "#RRGGBB" or "RRGGBB", in any case
+pub fn parse_hex_color(hex: &str) -> Option<Rgb<u8>> {
+    let hex = hex.trim();
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Rgb([channel(0)?, channel(2)?, channel(4)?]))
+}
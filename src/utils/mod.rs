@@ -1,3 +1,18 @@
+pub mod canvas_detection;
+pub mod checkpoint;
+pub mod draw_plan;
+pub mod fail_safe;
+pub mod game_profiles;
+pub mod hotkeys;
+pub mod humanize;
 pub mod image_drawing;
+pub mod image_metrics;
 pub mod image_utils;
-pub mod save_coordinates;
+pub mod palette_check;
+pub mod palette_detection;
+pub mod plan_render;
+pub mod preflight;
+pub mod profile;
+pub mod screen_source;
+pub mod screen_space;
+pub mod time_estimate;
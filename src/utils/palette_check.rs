@@ -0,0 +1,123 @@
+use druid::image::{ImageBuffer, Rgb};
+
+use super::image_utils::color_distance;
+
+// How far the color under a calibrated point may be from its palette color.
+// Screenshots are compressed and buttons are shaded, so it is not exact
+const MATCH_TOLERANCE: f64 = 48.0;
+// Pixels around the point that are averaged, in every direction
+const SAMPLE_RADIUS: i64 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PaletteMismatch {
+    pub index: usize,
+    pub expected: Rgb<u8>,
+    // None when the point is outside of the screenshot
+    pub found: Option<Rgb<u8>>,
+}
+
+// Average color of the pixels around `(x, y)`
+fn sample_color(screenshot: &ImageBuffer<Rgb<u8>, Vec<u8>>, (x, y): (f64, f64)) -> Option<Rgb<u8>> {
+    let (width, height) = screenshot.dimensions();
+    let (cx, cy) = (x.round() as i64, y.round() as i64);
+    if cx < 0 || cy < 0 || cx >= width as i64 || cy >= height as i64 {
+        return None;
+    }
+
+    let mut sum = [0u32; 3];
+    let mut count = 0;
+    for sy in (cy - SAMPLE_RADIUS).max(0)..=(cy + SAMPLE_RADIUS).min(height as i64 - 1) {
+        for sx in (cx - SAMPLE_RADIUS).max(0)..=(cx + SAMPLE_RADIUS).min(width as i64 - 1) {
+            let pixel = screenshot.get_pixel(sx as u32, sy as u32);
+            for channel in 0..3 {
+                sum[channel] += pixel[channel] as u32;
+            }
+            count += 1;
+        }
+    }
+    Some(Rgb(sum.map(|total| (total / count) as u8)))
+}
+
+// Compares the color under every calibrated point, in screenshot pixels, with
+// the palette. Uncalibrated colors are not checked
+pub fn check_palette(
+    screenshot: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    palette: &[Rgb<u8>],
+    points: &[Option<(f64, f64)>],
+) -> Vec<PaletteMismatch> {
+    palette
+        .iter()
+        .zip(points)
+        .enumerate()
+        .filter_map(|(index, (&expected, point))| {
+            let found = sample_color(screenshot, (*point)?);
+            let matches =
+                found.is_some_and(|found| color_distance(&found, &expected) <= MATCH_TOLERANCE);
+            (!matches).then_some(PaletteMismatch {
+                index,
+                expected,
+                found,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PALETTE: [Rgb<u8>; 4] = [
+        Rgb([0, 0, 0]),
+        Rgb([255, 0, 0]),
+        Rgb([0, 0, 255]),
+        Rgb([255, 255, 0]),
+    ];
+
+    // 10x10 swatches side by side, with `colors` instead of the palette
+    fn screenshot(colors: [Rgb<u8>; 4]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(40, 10, |x, _| colors[x as usize / 10])
+    }
+
+    fn centers() -> Vec<Option<(f64, f64)>> {
+        (0..4).map(|i| Some((i as f64 * 10.0 + 5.0, 5.0))).collect()
+    }
+
+    #[test]
+    fn one_wrong_swatch_is_reported() {
+        let mut colors = PALETTE;
+        colors[2] = Rgb([0, 200, 0]);
+        let mismatches = check_palette(&screenshot(colors), &PALETTE, &centers());
+        assert_eq!(
+            mismatches,
+            vec![PaletteMismatch {
+                index: 2,
+                expected: PALETTE[2],
+                found: Some(Rgb([0, 200, 0])),
+            }]
+        );
+    }
+
+    #[test]
+    fn shaded_buttons_still_match() {
+        let mut image = screenshot(PALETTE);
+        // A highlight right next to the point is averaged out
+        image.put_pixel(16, 5, Rgb([255, 120, 120]));
+        assert!(check_palette(&image, &PALETTE, &centers()).is_empty());
+    }
+
+    #[test]
+    fn uncalibrated_and_off_screen_points() {
+        let mut points = centers();
+        points[0] = None;
+        points[3] = Some((45.0, 5.0));
+        let mismatches = check_palette(&screenshot(PALETTE), &PALETTE, &points);
+        assert_eq!(
+            mismatches,
+            vec![PaletteMismatch {
+                index: 3,
+                expected: PALETTE[3],
+                found: None,
+            }]
+        );
+    }
+}
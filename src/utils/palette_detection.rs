@@ -0,0 +1,195 @@
+use druid::image::{ImageBuffer, Rgb};
+
+use super::image_utils::color_distance;
+
+// Colors closer than this to a palette color belong to its swatch. Screenshots
+// are often slightly off because of color management and compression
+const COLOR_TOLERANCE: f64 = 12.0;
+// Swatch buttons are small, roughly square and mostly filled with their color
+const MIN_SWATCH_SIZE: u32 = 6;
+const MAX_SWATCH_SIZE: u32 = 120;
+const MIN_FILL_RATIO: f64 = 0.6;
+
+struct Component {
+    label: usize,
+    pixels: u32,
+    min: (u32, u32),
+    max: (u32, u32),
+}
+
+impl Component {
+    fn width(&self) -> u32 {
+        self.max.0 - self.min.0 + 1
+    }
+
+    fn height(&self) -> u32 {
+        self.max.1 - self.min.1 + 1
+    }
+
+    fn center(&self) -> (f64, f64) {
+        (
+            (self.min.0 + self.max.0) as f64 / 2.0,
+            (self.min.1 + self.max.1) as f64 / 2.0,
+        )
+    }
+
+    fn is_swatch(&self) -> bool {
+        let (width, height) = (self.width(), self.height());
+        let fill_ratio = self.pixels as f64 / (width * height) as f64;
+        width.min(height) >= MIN_SWATCH_SIZE
+            && width.max(height) <= MAX_SWATCH_SIZE
+            && width.max(height) <= width.min(height) * 2
+            && fill_ratio >= MIN_FILL_RATIO
+    }
+}
+
+// Finds the button of every palette color in a screenshot of the game by
+// segmenting it into areas of a single palette color. Returns the centers of
+// the swatches in screenshot pixels, None for colors that weren't found
+pub fn detect_palette_buttons(
+    screenshot: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    palette: &[Rgb<u8>],
+) -> Vec<Option<(f64, f64)>> {
+    let (width, height) = screenshot.dimensions();
+    let labels: Vec<Option<usize>> = screenshot
+        .pixels()
+        .map(|pixel| {
+            palette
+                .iter()
+                .map(|color| color_distance(pixel, color))
+                .enumerate()
+                .filter(|&(_, distance)| distance <= COLOR_TOLERANCE)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(index, _)| index)
+        })
+        .collect();
+
+    let mut best: Vec<Option<Component>> = palette.iter().map(|_| None).collect();
+    for component in connected_components(&labels, width, height) {
+        if !component.is_swatch() {
+            continue;
+        }
+        let current = &mut best[component.label];
+        if current
+            .as_ref()
+            .is_none_or(|current| component.pixels > current.pixels)
+        {
+            *current = Some(component);
+        }
+    }
+
+    best.iter()
+        .map(|component| component.as_ref().map(Component::center))
+        .collect()
+}
+
+fn connected_components(labels: &[Option<usize>], width: u32, height: u32) -> Vec<Component> {
+    let mut visited = vec![false; labels.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..labels.len() {
+        let Some(label) = labels[start] else {
+            continue;
+        };
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+
+        let mut component = Component {
+            label,
+            pixels: 0,
+            min: (u32::MAX, u32::MAX),
+            max: (0, 0),
+        };
+        while let Some(index) = stack.pop() {
+            let (x, y) = ((index as u32) % width, (index as u32) / width);
+            component.pixels += 1;
+            component.min = (component.min.0.min(x), component.min.1.min(y));
+            component.max = (component.max.0.max(x), component.max.1.max(y));
+
+            let neighbours = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width as usize),
+                (y + 1 < height).then(|| index + width as usize),
+            ];
+            for neighbour in neighbours.into_iter().flatten() {
+                if !visited[neighbour] && labels[neighbour] == Some(label) {
+                    visited[neighbour] = true;
+                    stack.push(neighbour);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAY: Rgb<u8> = Rgb([90, 90, 90]);
+
+    // 20 pixel swatches with 10 pixels of gray between and around them
+    fn palette_screenshot(rows: &[&[Rgb<u8>]]) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0) as u32;
+        let mut screenshot =
+            ImageBuffer::from_pixel(10 + columns * 30, 10 + rows.len() as u32 * 30, GRAY);
+        for (row, colors) in rows.iter().enumerate() {
+            for (column, color) in colors.iter().enumerate() {
+                for y in 0..20 {
+                    for x in 0..20 {
+                        screenshot.put_pixel(
+                            10 + column as u32 * 30 + x,
+                            10 + row as u32 * 30 + y,
+                            *color,
+                        );
+                    }
+                }
+            }
+        }
+        screenshot
+    }
+
+    #[test]
+    fn finds_the_center_of_every_swatch() {
+        let red = Rgb([255, 0, 0]);
+        let green = Rgb([0, 200, 0]);
+        let blue = Rgb([0, 0, 255]);
+        let black = Rgb([0, 0, 0]);
+        let screenshot = palette_screenshot(&[&[red, green], &[blue, black]]);
+
+        let buttons = detect_palette_buttons(&screenshot, &[red, green, blue, black]);
+        assert_eq!(
+            buttons,
+            vec![
+                Some((19.5, 19.5)),
+                Some((49.5, 19.5)),
+                Some((19.5, 49.5)),
+                Some((49.5, 49.5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_colors_are_none() {
+        let red = Rgb([255, 0, 0]);
+        let screenshot = palette_screenshot(&[&[red]]);
+        let buttons = detect_palette_buttons(&screenshot, &[red, Rgb([255, 255, 0])]);
+        assert_eq!(buttons, vec![Some((19.5, 19.5)), None]);
+    }
+
+    #[test]
+    fn thin_lines_are_not_swatches() {
+        let red = Rgb([255, 0, 0]);
+        let mut screenshot = ImageBuffer::from_pixel(60, 20, GRAY);
+        for x in 0..60 {
+            screenshot.put_pixel(x, 10, red);
+        }
+        assert_eq!(detect_palette_buttons(&screenshot, &[red]), vec![None]);
+    }
+}
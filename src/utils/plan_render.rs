@@ -0,0 +1,72 @@
+use druid::image::{ImageBuffer, Rgb};
+
+use super::draw_plan::{DrawPlan, Line, BACKGROUND};
+
+// What the canvas should look like after drawing `plan` with a round brush of
+// `brush_width` pixels on an empty canvas of `size`
+pub fn render_plan(
+    plan: &DrawPlan,
+    size: (u32, u32),
+    brush_width: f64,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    render_plan_on(
+        ImageBuffer::from_pixel(size.0, size.1, BACKGROUND),
+        plan,
+        brush_width,
+    )
+}
+
+// Same as render_plan, on a canvas that already has content
+pub fn render_plan_on(
+    mut canvas: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    plan: &DrawPlan,
+    brush_width: f64,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let radius = (brush_width / 2.0).max(0.5);
+    if canvas.width() == 0 || canvas.height() == 0 {
+        return canvas;
+    }
+
+    for strokes in &plan.colors {
+        for line in &strokes.lines {
+            draw_stroke(&mut canvas, *line, radius, strokes.color);
+        }
+    }
+    canvas
+}
+
+fn draw_stroke(
+    canvas: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    ((x0, y0), (x1, y1)): Line,
+    radius: f64,
+    color: Rgb<u8>,
+) {
+    let (width, height) = canvas.dimensions();
+    let clamp = |value: f64, max: u32| value.clamp(0.0, max.saturating_sub(1) as f64) as u32;
+    let (min_x, max_x) = (
+        clamp(x0.min(x1) - radius, width),
+        clamp(x0.max(x1) + radius, width),
+    );
+    let (min_y, max_y) = (
+        clamp(y0.min(y1) - radius, height),
+        clamp(y0.max(y1) + radius, height),
+    );
+
+    let (dx, dy) = (x1 - x0, y1 - y0);
+    let length_squared = dx * dx + dy * dy;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            // Distance from the pixel to the closest point of the stroke
+            let (px, py) = (x as f64, y as f64);
+            let t = if length_squared == 0.0 {
+                0.0
+            } else {
+                (((px - x0) * dx + (py - y0) * dy) / length_squared).clamp(0.0, 1.0)
+            };
+            let (cx, cy) = (x0 + t * dx, y0 + t * dy);
+            if (px - cx).powi(2) + (py - cy).powi(2) <= radius * radius {
+                canvas.put_pixel(x, y, color);
+            }
+        }
+    }
+}
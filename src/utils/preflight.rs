@@ -0,0 +1,208 @@
+use std::fmt;
+
+use druid::image::Rgb;
+use druid::Rect;
+
+use super::image_utils::color_hex;
+
+#[derive(Debug)]
+pub struct PreflightCheck {
+    pub description: String,
+    pub passed: bool,
+}
+
+#[derive(Debug)]
+pub struct Preflight {
+    pub checks: Vec<PreflightCheck>,
+}
+
+impl Preflight {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    fn check(&mut self, passed: bool, description: impl Into<String>) {
+        self.checks.push(PreflightCheck {
+            description: description.into(),
+            passed,
+        });
+    }
+}
+
+impl fmt::Display for Preflight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let mark = if check.passed { "[x]" } else { "[ ]" };
+            writeln!(f, "{} {}", mark, check.description)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct PreflightInput<'a> {
+    pub area: Option<Rect>,
+    // Size of the area the loaded image was fitted into, if any
+    pub image_area: Option<(u32, u32)>,
+    pub image_size: (u32, u32),
+    // Colors the plan is going to draw
+    pub used_colors: &'a [Rgb<u8>],
+    pub palette: &'a [Rgb<u8>],
+    pub colors_pos: &'a [Option<(f64, f64)>],
+    // Screen rectangles of the bot's own windows
+    pub windows: &'a [Rect],
+}
+
+pub fn preflight(input: &PreflightInput) -> Preflight {
+    let mut result = Preflight { checks: Vec::new() };
+
+    let area = input
+        .area
+        .filter(|area| area.width() >= 1.0 && area.height() >= 1.0);
+    match input.area {
+        None => result.check(false, "Select the drawing area"),
+        Some(area) => result.check(
+            area.width() >= 1.0 && area.height() >= 1.0,
+            "Select a drawing area that is not empty",
+        ),
+    }
+
+    match input.image_area {
+        None => result.check(false, "Load an image"),
+        Some((width, height)) => {
+            // Fitting keeps the proportions, so one side fills the area and the other fits in it
+            let (image_width, image_height) = input.image_size;
+            result.check(
+                image_width <= width
+                    && image_height <= height
+                    && (image_width == width || image_height == height),
+                "Load an image",
+            );
+            // The image keeps its proportions, so only the area it was
+            // prepared for tells whether it is stale
+            if let Some(area) = area {
+                result.check(
+                    width == area.width() as u32 && height == area.height() as u32,
+                    format!(
+                        "Reload the image, it was prepared for a {}x{} area",
+                        width, height
+                    ),
+                );
+            }
+        }
+    }
+
+    let missing: Vec<String> = input
+        .used_colors
+        .iter()
+        .filter(|color| {
+            input
+                .palette
+                .iter()
+                .position(|c| c == *color)
+                .is_none_or(|index| input.colors_pos.get(index).copied().flatten().is_none())
+        })
+        .map(color_hex)
+        .collect();
+    if missing.is_empty() {
+        result.check(true, "Calibrate every color used by the image");
+    } else {
+        result.check(
+            false,
+            format!(
+                "Calibrate every color used by the image, missing: {}",
+                missing.join(", ")
+            ),
+        );
+    }
+
+    if let Some(area) = area {
+        let covered: Vec<String> = input
+            .colors_pos
+            .iter()
+            .enumerate()
+            .filter(|(_, pos)| pos.is_some_and(|pos| area.contains(pos.into())))
+            .map(|(index, _)| (index + 1).to_string())
+            .collect();
+        if covered.is_empty() {
+            result.check(true, "Keep palette buttons outside the drawing area");
+        } else {
+            result.check(
+                false,
+                format!(
+                    "Keep palette buttons outside the drawing area, covered: {}",
+                    covered.join(", ")
+                ),
+            );
+        }
+
+        let overlaps_window = input
+            .windows
+            .iter()
+            .any(|window| area.intersect(*window).area() > 0.0);
+        result.check(
+            !overlaps_window,
+            "Move the bot windows away from the drawing area",
+        );
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::image::DynamicImage;
+
+    use super::*;
+    use crate::utils::image_utils::resize_to_area;
+
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+    fn input<'a>(
+        area: Rect,
+        image_area: Option<(u32, u32)>,
+        image_size: (u32, u32),
+    ) -> PreflightInput<'a> {
+        PreflightInput {
+            area: Some(area),
+            image_area,
+            image_size,
+            used_colors: &[RED],
+            palette: &[RED],
+            colors_pos: &[Some((-10.0, -10.0))],
+            windows: &[],
+        }
+    }
+
+    #[test]
+    fn wide_image_in_square_area_passes() {
+        let image = resize_to_area(&DynamicImage::new_rgb8(200, 100), (100, 100));
+        assert_eq!(image.dimensions(), (100, 50));
+
+        let area = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let checklist = preflight(&input(area, Some((100, 100)), image.dimensions()));
+        assert!(checklist.passed(), "{}", checklist);
+    }
+
+    #[test]
+    fn image_for_another_area_fails() {
+        let area = Rect::new(0.0, 0.0, 100.0, 100.0);
+        let checklist = preflight(&input(area, Some((200, 100)), (200, 100)));
+        assert!(!checklist.passed());
+        assert!(checklist
+            .to_string()
+            .contains("prepared for a 200x100 area"));
+    }
+
+    #[test]
+    fn missing_image_fails() {
+        let area = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert!(!preflight(&input(area, None, (0, 0))).passed());
+    }
+
+    #[test]
+    fn image_not_fitted_to_its_area_fails() {
+        let area = Rect::new(0.0, 0.0, 100.0, 100.0);
+        assert!(!preflight(&input(area, Some((100, 100)), (80, 50))).passed());
+        assert!(!preflight(&input(area, Some((100, 100)), (100, 120))).passed());
+    }
+}
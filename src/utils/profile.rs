@@ -0,0 +1,371 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use druid::image::Rgb;
+use druid::{Rect, Vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::error::BotError;
+
+use super::time_estimate::DrawTimings;
+
+// Version 2 stores positions relative to `Profile::anchor`
+pub const PROFILE_VERSION: u32 = 2;
+pub const PROFILES_DIR: &str = "profiles";
+const SELECTED_PROFILE_FILE: &str = "profiles/selected.txt";
+// Name of the eraser in `Profile::tools`
+pub const ERASER_TOOL: &str = "eraser";
+
+// Before game profiles there was a single calibration, always for Gartic Phone
+const LEGACY_PROFILE_NAME: &str = "Gartic Phone";
+const LEGACY_COLORS_POS_FILE: &str = "colors_pos.txt";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaletteEntry {
+    pub rgb: [u8; 3],
+    // Position of the color's button relative to the anchor, None until calibrated
+    pub position: Option<(f64, f64)>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CanvasRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl From<Rect> for CanvasRect {
+    fn from(rect: Rect) -> Self {
+        CanvasRect {
+            x: rect.x0,
+            y: rect.y0,
+            width: rect.width(),
+            height: rect.height(),
+        }
+    }
+}
+
+impl From<CanvasRect> for Rect {
+    fn from(canvas: CanvasRect) -> Self {
+        Rect::new(
+            canvas.x,
+            canvas.y,
+            canvas.x + canvas.width,
+            canvas.y + canvas.height,
+        )
+    }
+}
+
+// How the palette buttons are arranged in the game. Colors are listed row by
+// row, left to right, and calibrated in the same order
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PaletteLayout {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl Default for PaletteLayout {
+    fn default() -> Self {
+        PaletteLayout {
+            columns: 1,
+            rows: 1,
+        }
+    }
+}
+
+// The screen the profile was calibrated on. Positions are only valid there
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScreenInfo {
+    pub width: f64,
+    pub height: f64,
+    pub scale_factor: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Profile {
+    pub version: u32,
+    pub name: String,
+    pub screen: Option<ScreenInfo>,
+    pub palette: Vec<PaletteEntry>,
+    #[serde(default)]
+    pub layout: PaletteLayout,
+    pub canvas: Option<CanvasRect>,
+    // Button positions of drawing tools (brush, eraser, fill...) by tool name
+    #[serde(default)]
+    pub tools: BTreeMap<String, (f64, f64)>,
+    // Measured on the last completed drawing, calibrates the time estimate
+    #[serde(default)]
+    pub timings: Option<DrawTimings>,
+    // Screen position of a reference point, the canvas corner or a point the
+    // user clicked. Every other position is relative to it, so moving the game
+    // only needs a new anchor. Without one positions are absolute
+    #[serde(default)]
+    pub anchor: Option<(f64, f64)>,
+}
+
+impl Profile {
+    pub fn new(name: &str, palette: &[Rgb<u8>], layout: PaletteLayout) -> Self {
+        Profile {
+            version: PROFILE_VERSION,
+            name: name.to_string(),
+            screen: None,
+            palette: palette
+                .iter()
+                .map(|color| PaletteEntry {
+                    rgb: color.0,
+                    position: None,
+                })
+                .collect(),
+            layout,
+            canvas: None,
+            tools: BTreeMap::new(),
+            timings: None,
+            anchor: None,
+        }
+    }
+
+    pub fn colors(&self) -> Vec<Rgb<u8>> {
+        self.palette.iter().map(|entry| Rgb(entry.rgb)).collect()
+    }
+
+    fn origin(&self) -> (f64, f64) {
+        self.anchor.unwrap_or((0.0, 0.0))
+    }
+
+    // Screen positions of the palette buttons
+    pub fn colors_pos(&self) -> Vec<Option<(f64, f64)>> {
+        let (x, y) = self.origin();
+        self.palette
+            .iter()
+            .map(|entry| entry.position.map(|pos| (pos.0 + x, pos.1 + y)))
+            .collect()
+    }
+
+    pub fn set_colors_pos(&mut self, colors_pos: &[Option<(f64, f64)>]) {
+        let (x, y) = self.origin();
+        for (index, entry) in self.palette.iter_mut().enumerate() {
+            entry.position = colors_pos
+                .get(index)
+                .copied()
+                .flatten()
+                .map(|pos| (pos.0 - x, pos.1 - y));
+        }
+    }
+
+    // The canvas on the screen
+    pub fn canvas_rect(&self) -> Option<Rect> {
+        let (x, y) = self.origin();
+        self.canvas
+            .map(|canvas| Rect::from(canvas) + Vec2::new(x, y))
+    }
+
+    pub fn set_canvas_rect(&mut self, canvas: Option<Rect>) {
+        let (x, y) = self.origin();
+        self.canvas = canvas.map(|canvas| CanvasRect::from(canvas - Vec2::new(x, y)));
+    }
+
+    pub fn tool(&self, name: &str) -> Option<(f64, f64)> {
+        let (x, y) = self.origin();
+        self.tools.get(name).map(|pos| (pos.0 + x, pos.1 + y))
+    }
+
+    pub fn set_tool(&mut self, name: &str, pos: (f64, f64)) {
+        let (x, y) = self.origin();
+        self.tools.insert(name.to_string(), (pos.0 - x, pos.1 - y));
+    }
+
+    // Makes `anchor` the reference point, every position stays where it is
+    pub fn set_anchor(&mut self, anchor: (f64, f64)) {
+        let origin = self.origin();
+        self.shift((origin.0 - anchor.0, origin.1 - anchor.1));
+        self.anchor = Some(anchor);
+    }
+
+    // The reference point is now at `anchor`, every position moves with it
+    pub fn move_anchor(&mut self, anchor: (f64, f64)) {
+        self.anchor = Some(anchor);
+    }
+
+    // Moves the stored positions, not the screen positions
+    fn shift(&mut self, (dx, dy): (f64, f64)) {
+        let shift = |pos: &mut (f64, f64)| {
+            pos.0 += dx;
+            pos.1 += dy;
+        };
+        self.palette
+            .iter_mut()
+            .filter_map(|entry| entry.position.as_mut())
+            .for_each(shift);
+        self.tools.values_mut().for_each(shift);
+        if let Some(canvas) = &mut self.canvas {
+            canvas.x += dx;
+            canvas.y += dy;
+        }
+    }
+
+    pub fn file_path(&self) -> PathBuf {
+        let file_name: String = self
+            .name
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        Path::new(PROFILES_DIR).join(format!("{}.json", file_name))
+    }
+
+    pub fn load(path: &Path) -> Result<Profile, BotError> {
+        let profile: Profile =
+            serde_json::from_str(&fs::read_to_string(path)?).map_err(BotError::Profile)?;
+        if profile.version > PROFILE_VERSION {
+            return Err(BotError::UnsupportedProfileVersion(profile.version));
+        }
+        Ok(profile)
+    }
+
+    pub fn save(&self) -> Result<(), BotError> {
+        fs::create_dir_all(PROFILES_DIR)?;
+        self.save_to(&self.file_path())
+    }
+
+    fn save_to(&self, path: &Path) -> Result<(), BotError> {
+        let json = serde_json::to_string_pretty(self).map_err(BotError::Profile)?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+// Loads the saved calibration for a game, falling back to its built-in
+// `template`. Gartic Phone calibrations from older versions are migrated
+pub fn load_or_migrate_profile(template: Profile) -> Result<Profile, BotError> {
+    match Profile::load(&template.file_path()) {
+        Err(BotError::Io(e)) if e.kind() == ErrorKind::NotFound => (),
+        result => return result,
+    }
+    if template.name != LEGACY_PROFILE_NAME {
+        return Ok(template);
+    }
+
+    match load_colors_pos(LEGACY_COLORS_POS_FILE) {
+        Ok(colors_pos) => {
+            let profile = migrate_colors_pos(template, &colors_pos);
+            profile.save()?;
+            Ok(profile)
+        }
+        Err(BotError::Io(e)) if e.kind() == ErrorKind::NotFound => Ok(template),
+        Err(e) => Err(e),
+    }
+}
+
+// The legacy positions are absolute and in palette order
+fn migrate_colors_pos(mut template: Profile, colors_pos: &[(f64, f64)]) -> Profile {
+    let colors_pos: Vec<Option<(f64, f64)>> = colors_pos.iter().copied().map(Some).collect();
+    template.set_colors_pos(&colors_pos);
+    template
+}
+
+pub fn load_selected_profile_name() -> Option<String> {
+    let name = fs::read_to_string(SELECTED_PROFILE_FILE).ok()?;
+    Some(name.trim().to_string())
+}
+
+pub fn save_selected_profile_name(name: &str) -> Result<(), BotError> {
+    fs::create_dir_all(PROFILES_DIR)?;
+    fs::write(SELECTED_PROFILE_FILE, name)?;
+    Ok(())
+}
+
+// Legacy format: one "x y" line per palette color, in palette order
+pub fn load_colors_pos(filename: &str) -> Result<Vec<(f64, f64)>, BotError> {
+    let file = File::open(filename)?;
+    let reader = BufReader::new(file);
+    let mut colors_pos = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        let coords = line
+            .split_whitespace()
+            .map(|s| {
+                s.parse::<f64>().map_err(|_| BotError::InvalidCoordinate {
+                    line: index + 1,
+                    value: s.to_string(),
+                })
+            })
+            .collect::<Result<Vec<f64>, BotError>>()?;
+        if coords.len() == 2 {
+            colors_pos.push((coords[0], coords[1]));
+        }
+    }
+    Ok(colors_pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::game_profiles::{builtin_profile, GARTIC_PHONE};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn colors_pos_txt_is_migrated_in_palette_order() {
+        let path = temp_path("colors_pos.txt");
+        fs::write(&path, "10 20\n30.5 40\n\n50 60\n").unwrap();
+        let colors_pos = load_colors_pos(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let template = builtin_profile(GARTIC_PHONE).unwrap();
+        let profile = migrate_colors_pos(template.clone(), &colors_pos);
+        assert_eq!(profile.colors(), template.colors());
+        let migrated = profile.colors_pos();
+        assert_eq!(
+            migrated[..3],
+            [Some((10.0, 20.0)), Some((30.5, 40.0)), Some((50.0, 60.0))]
+        );
+        assert!(migrated[3..].iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn invalid_colors_pos_txt_names_the_line() {
+        let path = temp_path("invalid_colors_pos.txt");
+        fs::write(&path, "10 20\n30 abc\n").unwrap();
+        let result = load_colors_pos(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(BotError::InvalidCoordinate { line: 2, ref value }) if value == "abc"
+        ));
+    }
+
+    #[test]
+    fn anchored_profile_round_trips() {
+        let colors_pos = [Some((110.0, 60.0)), None, Some((130.0, 80.0))];
+        let canvas = Rect::new(200.0, 100.0, 600.0, 400.0);
+        let mut profile = builtin_profile(GARTIC_PHONE).unwrap();
+        profile.set_anchor((100.0, 50.0));
+        profile.set_colors_pos(&colors_pos);
+        profile.set_canvas_rect(Some(canvas));
+        profile.set_tool(ERASER_TOOL, (150.0, 90.0));
+        // Stored relative to the anchor
+        assert_eq!(profile.palette[0].position, Some((10.0, 10.0)));
+        assert_eq!(profile.tools[ERASER_TOOL], (50.0, 40.0));
+
+        let path = temp_path("profile.json");
+        profile.save_to(&path).unwrap();
+        let mut loaded = Profile::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, profile);
+        assert_eq!(loaded.version, PROFILE_VERSION);
+        assert_eq!(loaded.colors_pos()[..3], colors_pos);
+        assert_eq!(loaded.canvas_rect(), Some(canvas));
+
+        // Everything moves along with the anchor
+        loaded.move_anchor((120.0, 40.0));
+        assert_eq!(loaded.colors_pos()[0], Some((130.0, 50.0)));
+        assert_eq!(loaded.tool(ERASER_TOOL), Some((170.0, 80.0)));
+        assert_eq!(loaded.canvas_rect(), Some(canvas + Vec2::new(20.0, -10.0)));
+    }
+}
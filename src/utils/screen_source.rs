@@ -0,0 +1,159 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use druid::image::{open, ImageBuffer, Rgb};
+use druid::Rect;
+
+use crate::error::BotError;
+
+use super::image_utils::crop_screenshot;
+
+pub type Capture = ImageBuffer<Rgb<u8>, Vec<u8>>;
+
+// Where the verification pass gets the current canvas from. A capture is the
+// drawing area only, one pixel per area pixel. None means the source has
+// nothing newer than its last capture
+pub trait ScreenSource: Send {
+    fn capture(&mut self) -> Result<Option<Capture>, BotError>;
+}
+
+// Reads a screenshot file on every capture, e.g. one a screenshot tool keeps
+// overwriting. A file that already has the size of the area is used as is,
+// otherwise `rect` is cut out of it
+pub struct FileScreenSource {
+    pub path: PathBuf,
+    // The drawing area in screenshot pixels
+    pub rect: Rect,
+    pub size: (u32, u32),
+    // Modification time of the file when it was last read, it is only read
+    // again once it changed
+    pub last_modified: Option<SystemTime>,
+}
+
+impl FileScreenSource {
+    // Ignores the file until it is written again, so a screenshot taken before
+    // drawing is never mistaken for the result
+    pub fn watch(path: PathBuf, rect: Rect, size: (u32, u32)) -> Self {
+        FileScreenSource {
+            last_modified: modified(&path),
+            path,
+            rect,
+            size,
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+impl ScreenSource for FileScreenSource {
+    fn capture(&mut self) -> Result<Option<Capture>, BotError> {
+        let modified = modified(&self.path);
+        if modified.is_some() && modified == self.last_modified {
+            return Ok(None);
+        }
+        self.last_modified = modified;
+
+        let screenshot = open(&self.path)?.to_rgb8();
+        if screenshot.dimensions() == self.size {
+            return Ok(Some(screenshot));
+        }
+        Ok(Some(crop_screenshot(&screenshot, self.rect, self.size)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use druid::image::ImageFormat;
+
+    use super::*;
+
+    fn write_screenshot(name: &str, screenshot: &Capture) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.png", name, std::process::id()));
+        screenshot
+            .save_with_format(&path, ImageFormat::Png)
+            .unwrap();
+        path
+    }
+
+    fn quadrants() -> Capture {
+        ImageBuffer::from_fn(40, 20, |x, y| match (x < 20, y < 10) {
+            (true, true) => Rgb([255, 0, 0]),
+            (false, true) => Rgb([0, 255, 0]),
+            (true, false) => Rgb([0, 0, 255]),
+            (false, false) => Rgb([255, 255, 255]),
+        })
+    }
+
+    #[test]
+    fn cuts_the_area_out_of_a_full_screenshot() {
+        let path = write_screenshot("full-screenshot", &quadrants());
+        let mut source = FileScreenSource {
+            path: path.clone(),
+            rect: Rect::new(20.0, 0.0, 40.0, 10.0),
+            size: (10, 5),
+            last_modified: None,
+        };
+        let capture = source.capture();
+        let _ = fs::remove_file(&path);
+
+        let capture = capture.unwrap().unwrap();
+        assert_eq!(capture.dimensions(), (10, 5));
+        assert!(capture.pixels().all(|pixel| *pixel == Rgb([0, 255, 0])));
+    }
+
+    #[test]
+    fn uses_a_screenshot_of_the_area_as_is() {
+        let screenshot = quadrants();
+        let path = write_screenshot("area-screenshot", &screenshot);
+        let mut source = FileScreenSource {
+            path: path.clone(),
+            rect: Rect::new(100.0, 100.0, 140.0, 120.0),
+            size: (40, 20),
+            last_modified: None,
+        };
+        let capture = source.capture();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(capture.unwrap(), Some(screenshot));
+    }
+
+    #[test]
+    fn watched_file_is_only_read_once_written_again() {
+        let path = write_screenshot("watched-screenshot", &quadrants());
+        let mut source =
+            FileScreenSource::watch(path.clone(), Rect::new(0.0, 0.0, 40.0, 20.0), (40, 20));
+        let before = source.capture().unwrap();
+
+        let later = SystemTime::now() + Duration::from_secs(5);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(later))
+            .unwrap();
+        let after = source.capture().unwrap();
+        let again = source.capture().unwrap();
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(before, None);
+        assert_eq!(after, Some(quadrants()));
+        assert_eq!(again, None);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let mut source = FileScreenSource {
+            path: std::env::temp_dir().join("no-such-screenshot.png"),
+            rect: Rect::new(0.0, 0.0, 10.0, 10.0),
+            size: (10, 10),
+            last_modified: None,
+        };
+        assert!(source.capture().is_err());
+    }
+}
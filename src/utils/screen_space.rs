@@ -0,0 +1,282 @@
+use druid::{Point, Rect, Screen, Vec2, WindowHandle};
+
+// One monitor as seen in both coordinate spaces. Logical coordinates are what
+// druid reports for windows and the pointer, physical ones are real pixels as
+// in screenshots and as the input backend expects them
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MonitorSpace {
+    pub physical: Rect,
+    pub logical: Rect,
+}
+
+impl MonitorSpace {
+    // druid divides physical coordinates by the scale of the window, origin included
+    pub fn with_scale(physical: Rect, scale: f64) -> Self {
+        MonitorSpace {
+            physical,
+            logical: physical.scale_from_origin(1.0 / scale),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenSpace {
+    monitors: Vec<MonitorSpace>,
+}
+
+impl ScreenSpace {
+    pub fn new(monitors: Vec<MonitorSpace>) -> Self {
+        ScreenSpace { monitors }
+    }
+
+    // Every monitor gets its own scale where the system reports it, otherwise
+    // the scale of the window is used for all of them. Monitor rectangles are
+    // physical pixels on Windows
+    pub fn current(window: &WindowHandle) -> Self {
+        let window_scale = window.get_scale().map_or(1.0, |scale| scale.x());
+        ScreenSpace::new(
+            Screen::get_monitors()
+                .iter()
+                .map(|monitor| {
+                    let physical = monitor.virtual_rect();
+                    let scale = monitor_scale(physical).unwrap_or(window_scale);
+                    MonitorSpace::with_scale(physical, scale)
+                })
+                .collect(),
+        )
+    }
+
+    pub fn physical_bounds(&self) -> Rect {
+        bounds(self.monitors.iter().map(|monitor| monitor.physical))
+    }
+
+    pub fn logical_bounds(&self) -> Rect {
+        bounds(self.monitors.iter().map(|monitor| monitor.logical))
+    }
+
+    pub fn to_physical(&self, point: Point) -> Point {
+        match self.monitor_at(point, |monitor| monitor.logical) {
+            Some(monitor) => map_point(point, monitor.logical, monitor.physical),
+            None => point,
+        }
+    }
+
+    pub fn to_logical(&self, point: Point) -> Point {
+        match self.monitor_at(point, |monitor| monitor.physical) {
+            Some(monitor) => map_point(point, monitor.physical, monitor.logical),
+            None => point,
+        }
+    }
+
+    // Screenshots cover every monitor and start at the top left one
+    pub fn screenshot_to_logical(&self, (x, y): (f64, f64)) -> Point {
+        self.to_logical(self.physical_bounds().origin() + Vec2::new(x, y))
+    }
+
+    pub fn logical_to_screenshot(&self, point: Point) -> Point {
+        self.to_physical(point) - self.physical_bounds().origin().to_vec2()
+    }
+
+    pub fn logical_rect_to_screenshot(&self, rect: Rect) -> Rect {
+        Rect::from_points(
+            self.logical_to_screenshot(rect.origin()),
+            self.logical_to_screenshot(Point::new(rect.x1, rect.y1)),
+        )
+    }
+
+    // rdev takes Windows coordinates relative to the top left of the virtual
+    // desktop, which is left of or above the primary monitor on some layouts
+    pub fn to_input(&self, physical: Point) -> Point {
+        if cfg!(windows) {
+            physical - self.physical_bounds().origin().to_vec2()
+        } else {
+            physical
+        }
+    }
+
+    // The monitor containing the point, or the closest one for points in the
+    // gaps between monitors of different scales
+    fn monitor_at(&self, point: Point, space: fn(&MonitorSpace) -> Rect) -> Option<&MonitorSpace> {
+        let distance = |rect: Rect| {
+            let dx = (rect.x0 - point.x).max(point.x - rect.x1).max(0.0);
+            let dy = (rect.y0 - point.y).max(point.y - rect.y1).max(0.0);
+            dx.hypot(dy)
+        };
+        self.monitors
+            .iter()
+            .min_by(|a, b| distance(space(a)).total_cmp(&distance(space(b))))
+    }
+}
+
+// Display scaling of the monitor covering `physical`, from its effective DPI
+#[cfg(windows)]
+fn monitor_scale(physical: Rect) -> Option<f64> {
+    #[repr(C)]
+    struct PointL {
+        x: i32,
+        y: i32,
+    }
+    const MONITOR_DEFAULTTONULL: u32 = 0;
+    const MDT_EFFECTIVE_DPI: i32 = 0;
+    // 100% scaling
+    const DEFAULT_DPI: f64 = 96.0;
+    #[link(name = "user32")]
+    extern "system" {
+        fn MonitorFromPoint(point: PointL, flags: u32) -> isize;
+    }
+    #[link(name = "shcore")]
+    extern "system" {
+        fn GetDpiForMonitor(monitor: isize, dpi_type: i32, dpi_x: *mut u32, dpi_y: *mut u32)
+            -> i32;
+    }
+
+    let center = physical.center();
+    let point = PointL {
+        x: center.x as i32,
+        y: center.y as i32,
+    };
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    // Needs the per-monitor DPI awareness druid enables, otherwise every
+    // monitor reports the system DPI
+    let result = unsafe {
+        let monitor = MonitorFromPoint(point, MONITOR_DEFAULTTONULL);
+        if monitor == 0 {
+            return None;
+        }
+        GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y)
+    };
+    (result == 0 && dpi_x > 0).then(|| dpi_x as f64 / DEFAULT_DPI)
+}
+
+#[cfg(not(windows))]
+fn monitor_scale(_physical: Rect) -> Option<f64> {
+    None
+}
+
+fn bounds(rects: impl Iterator<Item = Rect>) -> Rect {
+    rects.reduce(|a, b| a.union(b)).unwrap_or(Rect::ZERO)
+}
+
+fn map_point(point: Point, from: Rect, to: Rect) -> Point {
+    let scale_x = if from.width() == 0.0 {
+        1.0
+    } else {
+        to.width() / from.width()
+    };
+    let scale_y = if from.height() == 0.0 {
+        1.0
+    } else {
+        to.height() / from.height()
+    };
+    Point::new(
+        to.x0 + (point.x - from.x0) * scale_x,
+        to.y0 + (point.y - from.y0) * scale_y,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Point, expected: (f64, f64)) {
+        assert!(
+            (actual.x - expected.0).abs() < 1e-9 && (actual.y - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn single_monitor_at_150_percent() {
+        let screen = ScreenSpace::new(vec![MonitorSpace::with_scale(
+            Rect::new(0.0, 0.0, 2880.0, 1620.0),
+            1.5,
+        )]);
+        assert_eq!(screen.logical_bounds(), Rect::new(0.0, 0.0, 1920.0, 1080.0));
+        assert_near(screen.to_physical(Point::new(100.0, 200.0)), (150.0, 300.0));
+        assert_near(screen.to_logical(Point::new(150.0, 300.0)), (100.0, 200.0));
+    }
+
+    #[test]
+    fn secondary_monitor_right_of_primary() {
+        let screen = ScreenSpace::new(vec![
+            MonitorSpace::with_scale(Rect::new(0.0, 0.0, 1920.0, 1080.0), 1.0),
+            MonitorSpace::with_scale(Rect::new(1920.0, 0.0, 3840.0, 1080.0), 1.0),
+        ]);
+        assert_near(screen.to_physical(Point::new(2000.0, 10.0)), (2000.0, 10.0));
+        assert_near(screen.to_input(Point::new(2000.0, 10.0)), (2000.0, 10.0));
+    }
+
+    #[test]
+    fn mixed_scales_map_each_monitor_on_its_own() {
+        let primary = MonitorSpace {
+            physical: Rect::new(0.0, 0.0, 3840.0, 2160.0),
+            logical: Rect::new(0.0, 0.0, 1920.0, 1080.0),
+        };
+        let secondary = MonitorSpace {
+            physical: Rect::new(3840.0, 0.0, 5760.0, 1080.0),
+            logical: Rect::new(1920.0, 0.0, 3840.0, 1080.0),
+        };
+        let screen = ScreenSpace::new(vec![primary, secondary]);
+
+        assert_near(
+            screen.to_physical(Point::new(960.0, 540.0)),
+            (1920.0, 1080.0),
+        );
+        assert_near(
+            screen.to_physical(Point::new(2020.0, 540.0)),
+            (3940.0, 540.0),
+        );
+        assert_near(
+            screen.to_logical(Point::new(3940.0, 540.0)),
+            (2020.0, 540.0),
+        );
+    }
+
+    #[test]
+    fn monitor_left_of_primary_has_negative_coordinates() {
+        let screen = ScreenSpace::new(vec![
+            MonitorSpace::with_scale(Rect::new(0.0, 0.0, 1920.0, 1080.0), 1.0),
+            MonitorSpace::with_scale(Rect::new(-1280.0, 0.0, 0.0, 1024.0), 1.0),
+        ]);
+        assert_eq!(
+            screen.physical_bounds(),
+            Rect::new(-1280.0, 0.0, 1920.0, 1080.0)
+        );
+        // The screenshot starts at the left monitor
+        assert_near(screen.screenshot_to_logical((1280.0, 0.0)), (0.0, 0.0));
+        assert_near(screen.screenshot_to_logical((0.0, 10.0)), (-1280.0, 10.0));
+        assert_eq!(
+            screen.logical_rect_to_screenshot(Rect::new(-100.0, 0.0, 100.0, 50.0)),
+            Rect::new(1180.0, 0.0, 1380.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn points_in_gaps_use_the_closest_monitor() {
+        let screen = ScreenSpace::new(vec![
+            MonitorSpace::with_scale(Rect::new(0.0, 0.0, 1000.0, 1000.0), 2.0),
+            MonitorSpace::with_scale(Rect::new(0.0, 1000.0, 1000.0, 2000.0), 1.0),
+        ]);
+        // The first monitor ends at 500 logical, the second one starts at 1000
+        assert_near(
+            screen.to_physical(Point::new(100.0, 600.0)),
+            (200.0, 1200.0),
+        );
+        assert_near(screen.to_physical(Point::new(100.0, 900.0)), (100.0, 900.0));
+    }
+
+    #[test]
+    fn round_trip_through_physical() {
+        let screen = ScreenSpace::new(vec![
+            MonitorSpace::with_scale(Rect::new(0.0, 0.0, 2560.0, 1440.0), 1.25),
+            MonitorSpace::with_scale(Rect::new(-1920.0, -200.0, 0.0, 880.0), 1.25),
+        ]);
+        for point in [(10.0, 20.0), (-700.0, 300.0), (2000.0, 1100.0)] {
+            let point = Point::new(point.0, point.1);
+            let back = screen.to_logical(screen.to_physical(point));
+            assert_near(back, (point.x, point.y));
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::draw_plan::DrawPlan;
+use super::humanize::{approach_moves, stroke_moves, HumanizeConfig};
+use super::image_drawing::{INPUT_COST, STROKE_DELAY};
+
+// A press and a release for every color and stroke
+const CLICK_EVENTS: usize = 2;
+
+// What the executor measured while drawing, pauses excluded
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DrawTimings {
+    pub strokes: usize,
+    pub colors: usize,
+    // Distance the pointer travelled between and along strokes, in pixels
+    pub travel: f64,
+    // Seconds spent on strokes, delays included
+    pub stroke_time: f64,
+    // Seconds spent on picking colors
+    pub color_time: f64,
+    // Mean humanize delay of the run in seconds, 0 without humanize, and how
+    // many delays stroke_time and color_time include
+    #[serde(default)]
+    pub mean_delay: f64,
+    #[serde(default)]
+    pub stroke_delays: usize,
+    #[serde(default)]
+    pub color_delays: usize,
+}
+
+// The work a plan takes, in the same units as DrawTimings
+pub struct PlanCost {
+    pub strokes: usize,
+    pub colors: usize,
+    pub travel: f64,
+    // Input events that are delayed when the motion is humanized
+    pub humanized_events: usize,
+}
+
+impl PlanCost {
+    pub fn of(plan: &DrawPlan) -> Self {
+        let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1);
+        let mut travel = 0.0;
+        let mut humanized_events = 0;
+        for strokes in &plan.colors {
+            // The pointer comes from the palette before the first stroke of a color
+            let mut last = None;
+            humanized_events += approach_moves(None, (0.0, 0.0)) + CLICK_EVENTS;
+            for &(start, end) in &strokes.lines {
+                travel += last.map_or(0.0, |last| distance(last, start)) + distance(start, end);
+                humanized_events +=
+                    approach_moves(last, start) + stroke_moves(start, end) + CLICK_EVENTS;
+                last = Some(end);
+            }
+        }
+        PlanCost {
+            strokes: plan.stroke_count(),
+            colors: plan.colors.len(),
+            travel,
+            humanized_events,
+        }
+    }
+}
+
+// Expected drawing time of a plan. Without measurements of a real run the
+// executor's delays are all we know; with them, the time per stroke is split
+// into the fixed delays and a cost per pixel of pointer travel. The humanize
+// delays of the measured run are taken out and those of the current settings
+// added, so toggling humanize keeps the measurements
+pub fn estimate_duration(
+    plan: &DrawPlan,
+    timings: Option<&DrawTimings>,
+    humanize: &HumanizeConfig,
+) -> Duration {
+    let cost = PlanCost::of(plan);
+    let mut per_stroke = (STROKE_DELAY + INPUT_COST).as_secs_f64();
+    let mut per_color = INPUT_COST.as_secs_f64();
+    let mut per_pixel = 0.0;
+
+    if let Some(timings) = timings.filter(|timings| timings.strokes > 0) {
+        let stroke_time =
+            (timings.stroke_time - timings.mean_delay * timings.stroke_delays as f64).max(0.0);
+        let color_time =
+            (timings.color_time - timings.mean_delay * timings.color_delays as f64).max(0.0);
+        let fixed = per_stroke * timings.strokes as f64;
+        if stroke_time > fixed && timings.travel > 0.0 {
+            per_pixel = (stroke_time - fixed) / timings.travel;
+        } else {
+            per_stroke = stroke_time / timings.strokes as f64;
+        }
+        if timings.colors > 0 {
+            per_color = color_time / timings.colors as f64;
+        }
+    }
+    let humanize_delays = if humanize.enabled {
+        humanize.mean_delay().as_secs_f64() * cost.humanized_events as f64
+    } else {
+        0.0
+    };
+
+    Duration::from_secs_f64(
+        per_stroke * cost.strokes as f64
+            + per_color * cost.colors as f64
+            + per_pixel * cost.travel
+            + humanize_delays,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use druid::image::Rgb;
+
+    use super::*;
+    use crate::utils::draw_plan::ColorStrokes;
+
+    fn plan() -> DrawPlan {
+        DrawPlan {
+            colors: vec![
+                ColorStrokes {
+                    color: Rgb([255, 0, 0]),
+                    lines: vec![((0.0, 0.0), (10.0, 0.0)), ((0.0, 5.0), (10.0, 5.0))],
+                },
+                ColorStrokes {
+                    color: Rgb([0, 0, 255]),
+                    lines: vec![((0.0, 10.0), (0.0, 30.0))],
+                },
+            ],
+        }
+    }
+
+    fn humanize(enabled: bool) -> HumanizeConfig {
+        HumanizeConfig {
+            enabled,
+            min_delay: 4.0,
+            max_delay: 6.0,
+            ..HumanizeConfig::default()
+        }
+    }
+
+    // A run of `plan()` taking 10 ms per stroke plus 1 ms per pixel, and 20 ms per color
+    fn measured(humanize: &HumanizeConfig) -> DrawTimings {
+        let cost = PlanCost::of(&plan());
+        let mean_delay = if humanize.enabled {
+            humanize.mean_delay().as_secs_f64()
+        } else {
+            0.0
+        };
+        // Every color change is an approach and a click
+        let color_delays = cost.colors * (approach_moves(None, (0.0, 0.0)) + CLICK_EVENTS);
+        let stroke_delays = if humanize.enabled {
+            cost.humanized_events - color_delays
+        } else {
+            0
+        };
+        DrawTimings {
+            strokes: cost.strokes,
+            colors: cost.colors,
+            travel: cost.travel,
+            stroke_time: 0.010 * cost.strokes as f64
+                + 0.001 * cost.travel
+                + mean_delay * stroke_delays as f64,
+            color_time: 0.020 * cost.colors as f64 + mean_delay * color_delays as f64,
+            mean_delay,
+            stroke_delays,
+            color_delays: if humanize.enabled { color_delays } else { 0 },
+        }
+    }
+
+    fn assert_close(actual: Duration, expected: f64) {
+        assert!(
+            (actual.as_secs_f64() - expected).abs() < 1e-9,
+            "{:?} is not {}s",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn without_timings_the_executor_delays_are_used() {
+        let cost = PlanCost::of(&plan());
+        let plain =
+            (STROKE_DELAY + INPUT_COST).as_secs_f64() * 3.0 + INPUT_COST.as_secs_f64() * 2.0;
+        assert_close(estimate_duration(&plan(), None, &humanize(false)), plain);
+
+        let delays = 0.005 * cost.humanized_events as f64;
+        assert_close(
+            estimate_duration(&plan(), None, &humanize(true)),
+            plain + delays,
+        );
+    }
+
+    #[test]
+    fn measured_timings_reproduce_the_measured_run() {
+        for enabled in [false, true] {
+            let humanize = humanize(enabled);
+            let timings = measured(&humanize);
+            assert_close(
+                estimate_duration(&plan(), Some(&timings), &humanize),
+                timings.stroke_time + timings.color_time,
+            );
+        }
+    }
+
+    #[test]
+    fn humanize_delays_follow_the_current_setting() {
+        let plain = measured(&humanize(false));
+        let humanized = measured(&humanize(true));
+        let delays = 0.005 * PlanCost::of(&plan()).humanized_events as f64;
+        let plain_time = plain.stroke_time + plain.color_time;
+
+        // A humanized run estimates a plain one without its delays
+        assert_close(
+            estimate_duration(&plan(), Some(&humanized), &humanize(false)),
+            plain_time,
+        );
+        // And a plain run gets the delays of the current settings
+        let slower = HumanizeConfig {
+            min_delay: 8.0,
+            max_delay: 12.0,
+            ..humanize(true)
+        };
+        assert_close(
+            estimate_duration(&plan(), Some(&plain), &slower),
+            plain_time + 2.0 * delays,
+        );
+        assert_close(
+            estimate_duration(&plan(), Some(&humanized), &slower),
+            plain_time + 2.0 * delays,
+        );
+    }
+}